@@ -0,0 +1,235 @@
+//! Expansion of `#[derive(AsRef)]` and `#[derive(AsMut)]`.
+
+use crate::{attr, target, DerefTarget, Errors};
+
+/// A field marked with `#[as_ref]`.
+struct AsRefField {
+    member: syn::Member,
+    ty: syn::Type,
+    /// `#[as_ref(forward)]`.
+    forward: Option<syn::Ident>,
+}
+
+impl AsRefField {
+    /// Returns the fields marked with `#[as_ref]`.
+    fn get(input: &syn::DeriveInput, errors: &mut Errors) -> Vec<Self> {
+        let mut marked = Vec::new();
+
+        for attr in input.attrs.iter() {
+            if attr.path.is_ident(attr::AS_REF_ATTR_NAME) {
+                errors.push(syn::Error::new_spanned(
+                    attr,
+                    "#[as_ref] is not allowed on the type, mark a field instead",
+                ));
+            }
+        }
+
+        match input.data {
+            syn::Data::Struct(ref data) => {
+                for (i, field) in data.fields.iter().enumerate() {
+                    let options = attr::AsRefOptions::parse(&field.attrs, errors);
+
+                    if options.attr.is_some() {
+                        marked.push(Self {
+                            member: target::member(i, field),
+                            ty: field.ty.clone(),
+                            forward: options.forward,
+                        });
+                    }
+                }
+            }
+            syn::Data::Enum(ref data) => {
+                for variant in data.variants.iter() {
+                    for attr in variant.attrs.iter() {
+                        if attr.path.is_ident(attr::AS_REF_ATTR_NAME) {
+                            errors.push(syn::Error::new_spanned(
+                                attr,
+                                "#[as_ref] is not supported on enums, use #[deref] instead",
+                            ));
+                        }
+                    }
+                }
+
+                let fields = data
+                    .variants
+                    .iter()
+                    .flat_map(|variant| variant.fields.iter());
+
+                for field in fields {
+                    let options = attr::AsRefOptions::parse(&field.attrs, errors);
+
+                    if let Some(attr) = options.attr {
+                        errors.push(syn::Error::new_spanned(
+                            attr,
+                            "#[as_ref] is not supported on enums, use #[deref] instead",
+                        ));
+                    }
+                }
+            }
+            syn::Data::Union(_) => {}
+        }
+
+        marked
+    }
+}
+
+/// Expands `AsRef`, or `AsMut` if `mutable`.
+///
+/// Every field marked with `#[as_ref]` gets an impl for its type, or a blanket impl
+/// with `#[as_ref(forward)]`. Without marked fields the target of `Deref` is used.
+pub fn expand(input: &syn::DeriveInput, mutable: bool) -> syn::Result<proc_macro2::TokenStream> {
+    let mut errors = Errors::default();
+    let fields = AsRefField::get(input, &mut errors);
+
+    if fields.is_empty() {
+        errors.finish()?;
+
+        let target = DerefTarget::get(input)?;
+        let target_ty = target.target_ty();
+        let impls = impl_as_ref(
+            input,
+            mutable,
+            &quote::quote!(#target_ty),
+            &target.target_ref(mutable),
+        );
+        let required = target.require_derives(input, None);
+
+        return Ok(quote::quote!(#impls #required));
+    }
+
+    for field in fields.iter() {
+        if let (Some(forward), true) = (&field.forward, fields.len() > 1) {
+            errors.push(syn::Error::new(
+                forward.span(),
+                "#[as_ref(forward)] conflicts with every other #[as_ref] field",
+            ));
+        }
+
+        let same = fields
+            .iter()
+            .filter(|f| target::same_type(&f.ty, &field.ty));
+
+        if same.count() > 1 {
+            let ty = &field.ty;
+
+            errors.push(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "conflicting #[as_ref] fields of type `{}`, mark only one of them",
+                    quote::quote!(#ty),
+                ),
+            ));
+        }
+    }
+
+    errors.finish()?;
+
+    let impls = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let member = &field.member;
+        let mutability = mutable.then(<syn::Token![mut]>::default);
+
+        if field.forward.is_some() {
+            return impl_forward(input, mutable, field);
+        }
+
+        impl_as_ref(
+            input,
+            mutable,
+            &quote::quote!(#ty),
+            &quote::quote!(&#mutability self.#member),
+        )
+    });
+
+    // The options on the type still need their derive, if any.
+    let has_options = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident(attr::ATTR_NAME));
+    let required = if has_options {
+        DerefTarget::get(input)?.require_derives(input, None)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    Ok(quote::quote!(#(#impls)* #required))
+}
+
+/// Implements `AsRef<U>` for every `U` the type of `field` implements `AsRef<U>` for.
+fn impl_forward(
+    input: &syn::DeriveInput,
+    mutable: bool,
+    field: &AsRefField,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let ty = &field.ty;
+    let member = &field.member;
+
+    let (trait_, method, mutability) = if mutable {
+        (
+            quote::quote!(::core::convert::AsMut),
+            quote::quote!(as_mut),
+            Some(<syn::Token![mut]>::default()),
+        )
+    } else {
+        (
+            quote::quote!(::core::convert::AsRef),
+            quote::quote!(as_ref),
+            None,
+        )
+    };
+
+    let mut generics = input.generics.clone();
+    generics
+        .params
+        .push(syn::parse_quote!(__AsRefTarget: ?::core::marker::Sized));
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#ty: #trait_<__AsRefTarget>));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_<__AsRefTarget> for #ident #ty_generics #where_clause {
+            #[inline(always)]
+            fn #method(&#mutability self) -> &#mutability __AsRefTarget {
+                #trait_::#method(&#mutability self.#member)
+            }
+        }
+    }
+}
+
+fn impl_as_ref(
+    input: &syn::DeriveInput,
+    mutable: bool,
+    ty: &proc_macro2::TokenStream,
+    expr: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if mutable {
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::convert::AsMut<#ty> for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn as_mut(&mut self) -> &mut #ty {
+                    #expr
+                }
+            }
+        }
+    } else {
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::convert::AsRef<#ty> for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn as_ref(&self) -> &#ty {
+                    #expr
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,630 @@
+//! Parsing of the `#[deref(...)]` and `#[as_ref(...)]` attributes.
+//!
+//! `#[deref]` is accepted in two positions, on fields and on the type itself,
+//! each with its own set of options, `#[as_ref]` only on fields. An attribute is
+//! either bare, `#[deref]`, or a comma separated list of options,
+//! `#[deref(key, key = value, key(...))]`. Every option may appear at most once
+//! per item, even when it's spread over several attributes.
+
+use syn::{
+    parse::{Parse, ParseStream, Parser},
+    spanned::Spanned,
+};
+
+use crate::Errors;
+
+/// The name of the `#[deref]` attribute.
+pub const ATTR_NAME: &str = "deref";
+
+/// The name of the `#[as_ref]` attribute.
+pub const AS_REF_ATTR_NAME: &str = "as_ref";
+
+/// A set of options accepted by an attribute in one position.
+///
+/// To add a new option, add it to [`Options::KEYS`] and parse its value in
+/// [`Options::parse_option`].
+pub trait Options: Default {
+    /// The name of the attribute.
+    const ATTR: &'static str;
+
+    /// Where the options are accepted, used in error messages.
+    const POSITION: &'static str;
+
+    /// Every option accepted in this position.
+    const KEYS: &'static [&'static str];
+
+    /// Parses the rest of the option `key`, `key` is always one of [`Options::KEYS`].
+    fn parse_option(&mut self, key: &syn::Ident, input: ParseStream) -> syn::Result<()>;
+}
+
+/// Options of `#[deref]` attributes on a field.
+#[derive(Default)]
+pub struct FieldOptions {
+    /// The first `#[deref]` attribute on the field.
+    pub attr: Option<syn::Attribute>,
+    /// `forward`, deref through the field's own `Deref`.
+    pub forward: Option<syn::Ident>,
+    /// `target = Type`, deref to `Type` by coercing the field.
+    pub target: Option<syn::Type>,
+    /// `ignore`, never select the field automatically.
+    pub ignore: Option<syn::Ident>,
+    /// `default = expr`, initializes the field in `From<Target>`, implies `ignore`.
+    pub default: Option<proc_macro2::TokenStream>,
+}
+
+impl Options for FieldOptions {
+    const ATTR: &'static str = ATTR_NAME;
+    const POSITION: &'static str = "fields";
+    const KEYS: &'static [&'static str] = &["forward", "target", "ignore", "default"];
+
+    fn parse_option(&mut self, key: &syn::Ident, input: ParseStream) -> syn::Result<()> {
+        match key.to_string().as_str() {
+            "forward" => self.forward = Some(flag(key, input)?),
+            "target" => self.target = Some(value(key, input)?),
+            "ignore" => self.ignore = Some(flag(key, input)?),
+            "default" => self.default = Some(expr(key, input)?),
+            _ => unreachable!("unknown option `{}`", key),
+        }
+
+        Ok(())
+    }
+}
+
+/// Options of `#[deref(...)]` attributes on the type itself.
+#[derive(Default)]
+pub struct TypeOptions {
+    /// Every option given, in order.
+    pub keys: Vec<syn::Ident>,
+    /// `forward`, deref through the target field's own `Deref`.
+    pub forward: Option<syn::Ident>,
+    /// `target = Type`, deref to `Type` by coercing the target field.
+    pub target: Option<syn::Type>,
+    /// `field = name` or `field = 0`, the target field.
+    pub field: Option<syn::Member>,
+    /// `path = a.b.c`, the path to the target starting at a field.
+    pub path: Option<FieldPath>,
+    /// `borrow(Hash, PartialEq, ...)`, traits forwarded to the target by `Borrow`.
+    pub borrow: Vec<syn::Ident>,
+    /// `from`, implement `From` for the type of the target field.
+    pub from: Option<syn::Ident>,
+    /// `into_inner`, add `into_inner` and implement `From<Self>` for the target field.
+    pub into_inner: Option<syn::Ident>,
+    /// `into_parts`, add `into_parts` returning every field.
+    pub into_parts: Option<syn::Ident>,
+    /// `index = Type`, index with `Type` instead of the target's own indices.
+    pub index: Option<syn::Type>,
+    /// `fmt(Display, Debug, ...)`, formatting traits forwarded to the target.
+    pub fmt: Vec<syn::Ident>,
+    /// `error` or `error = "context"`, make the type a transparent error.
+    pub error: Option<ErrorOption>,
+    /// `io(Read, Write, ...)`, `std::io` traits forwarded to the target.
+    pub io: Vec<syn::Ident>,
+    /// `pin`, add `project` pinning the target field structurally.
+    pub pin: Option<syn::Ident>,
+    /// `future`, implement `Future` by polling the pinned target field.
+    pub future: Option<syn::Ident>,
+    /// `ops(Add, Neg, AddAssign, Sum, ...)`, operators forwarded to the target field.
+    pub ops: Vec<syn::Ident>,
+    /// `cmp(PartialEq, PartialOrd)`, comparisons between the type and its target.
+    pub cmp: Vec<syn::Ident>,
+}
+
+impl Options for TypeOptions {
+    const ATTR: &'static str = ATTR_NAME;
+    const POSITION: &'static str = "types";
+    const KEYS: &'static [&'static str] = &[
+        "forward",
+        "target",
+        "field",
+        "path",
+        "borrow",
+        "from",
+        "into_inner",
+        "into_parts",
+        "index",
+        "fmt",
+        "error",
+        "io",
+        "pin",
+        "future",
+        "ops",
+        "cmp",
+    ];
+
+    fn parse_option(&mut self, key: &syn::Ident, input: ParseStream) -> syn::Result<()> {
+        self.keys.push(key.clone());
+
+        match key.to_string().as_str() {
+            "forward" => self.forward = Some(flag(key, input)?),
+            "target" => self.target = Some(value(key, input)?),
+            "field" => self.field = Some(value(key, input)?),
+            "path" => self.path = Some(value(key, input)?),
+            "borrow" => self.borrow = list(key, input, BORROW_TRAITS)?,
+            "from" => self.from = Some(flag(key, input)?),
+            "into_inner" => self.into_inner = Some(flag(key, input)?),
+            "into_parts" => self.into_parts = Some(flag(key, input)?),
+            "index" => self.index = Some(value(key, input)?),
+            "fmt" => self.fmt = list(key, input, FMT_TRAITS)?,
+            "error" => self.error = Some(ErrorOption::parse(key, input)?),
+            "io" => self.io = list(key, input, IO_TRAITS)?,
+            "pin" => self.pin = Some(flag(key, input)?),
+            "future" => self.future = Some(flag(key, input)?),
+            "ops" => self.ops = list(key, input, OPS_TRAITS)?,
+            "cmp" => self.cmp = list(key, input, CMP_TRAITS)?,
+            _ => unreachable!("unknown option `{}`", key),
+        }
+
+        Ok(())
+    }
+}
+
+/// Options of `#[as_ref]` attributes on a field.
+#[derive(Default)]
+pub struct AsRefOptions {
+    /// The first `#[as_ref]` attribute on the field.
+    pub attr: Option<syn::Attribute>,
+    /// `forward`, implement `AsRef<U>` for every `U` the field implements it for.
+    pub forward: Option<syn::Ident>,
+}
+
+impl Options for AsRefOptions {
+    const ATTR: &'static str = AS_REF_ATTR_NAME;
+    const POSITION: &'static str = "fields";
+    const KEYS: &'static [&'static str] = &["forward"];
+
+    fn parse_option(&mut self, key: &syn::Ident, input: ParseStream) -> syn::Result<()> {
+        match key.to_string().as_str() {
+            "forward" => self.forward = Some(flag(key, input)?),
+            _ => unreachable!("unknown option `{}`", key),
+        }
+
+        Ok(())
+    }
+}
+
+impl FieldOptions {
+    /// Parses every `#[deref]` attribute in `attrs`, pushing errors to `errors`.
+    pub fn parse(attrs: &[syn::Attribute], errors: &mut Errors) -> Self {
+        let mut options = Self::default();
+        let bare = parse_marker::<Self>(attrs, &mut options.attr, errors);
+
+        parse_options(attrs, &mut options, errors);
+
+        if let Some((key, span)) = options.ignored() {
+            if bare || options.forward.is_some() || options.target.is_some() {
+                errors.push(syn::Error::new(
+                    span,
+                    format!(
+                        "`{}` can't be combined with options of the target field",
+                        key
+                    ),
+                ));
+            }
+        }
+
+        options
+    }
+
+    /// Returns the option excluding the field from being the target, if any.
+    pub fn ignored(&self) -> Option<(&'static str, proc_macro2::Span)> {
+        match (&self.ignore, &self.default) {
+            (Some(ignore), _) => Some(("ignore", ignore.span())),
+            (None, Some(default)) => Some(("default", default.span())),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns whether the field is marked as the target.
+    pub fn is_marked(&self) -> bool {
+        self.attr.is_some() && self.ignored().is_none()
+    }
+}
+
+impl AsRefOptions {
+    /// Parses every `#[as_ref]` attribute in `attrs`, pushing errors to `errors`.
+    pub fn parse(attrs: &[syn::Attribute], errors: &mut Errors) -> Self {
+        let mut options = Self::default();
+
+        parse_marker::<Self>(attrs, &mut options.attr, errors);
+        parse_options(attrs, &mut options, errors);
+
+        options
+    }
+}
+
+impl TypeOptions {
+    /// Parses every `#[deref(...)]` attribute in `attrs`, pushing errors to `errors`.
+    pub fn parse(attrs: &[syn::Attribute], errors: &mut Errors) -> Self {
+        let mut options = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident(ATTR_NAME)) {
+            if attr.tokens.is_empty() {
+                errors.push(syn::Error::new_spanned(
+                    attr,
+                    "expected options on the type: #[deref(...)]",
+                ));
+            }
+        }
+
+        parse_options(attrs, &mut options, errors);
+
+        require_supertraits("borrow", &options.borrow, errors);
+        require_supertraits("cmp", &options.cmp, errors);
+
+        options
+    }
+}
+
+/// Traits accepted in lists paired with a supertrait that must be listed with them,
+/// as both have to compare the same way.
+const SUPERTRAITS: &[(&str, &str)] = &[
+    ("Eq", "PartialEq"),
+    ("PartialOrd", "PartialEq"),
+    ("Ord", "Eq"),
+    ("Ord", "PartialOrd"),
+];
+
+/// Pushes an error for every trait in `key(...)` listed without its supertraits.
+fn require_supertraits(key: &str, traits: &[syn::Ident], errors: &mut Errors) {
+    for trait_ in traits.iter() {
+        for (_, supertrait) in SUPERTRAITS.iter().filter(|(sub, _)| trait_ == sub) {
+            if !traits.iter().any(|other| other == supertrait) {
+                errors.push(syn::Error::new(
+                    trait_.span(),
+                    format!(
+                        "`{}` requires `{}`, add it to `{}(...)`",
+                        trait_, supertrait, key
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// The derive generating the items of each option on the type, the options missing
+/// here only select the target and are read by every derive.
+pub const OPTION_DERIVES: &[(&str, &str)] = &[
+    ("borrow", "Borrow"),
+    ("index", "Index"),
+    ("from", "Deref"),
+    ("into_inner", "Deref"),
+    ("into_parts", "Deref"),
+    ("fmt", "Deref"),
+    ("error", "Deref"),
+    ("io", "Deref"),
+    ("pin", "Deref"),
+    ("future", "Deref"),
+    ("ops", "Deref"),
+    ("cmp", "Deref"),
+];
+
+/// Stores the first attribute for `T` of `attrs` in `attr`, returning whether a bare
+/// attribute was found.
+fn parse_marker<T: Options>(
+    attrs: &[syn::Attribute],
+    attr: &mut Option<syn::Attribute>,
+    errors: &mut Errors,
+) -> bool {
+    let mut bare = false;
+
+    for a in attrs.iter().filter(|a| a.path.is_ident(T::ATTR)) {
+        if a.tokens.is_empty() {
+            if bare {
+                errors.push(syn::Error::new_spanned(
+                    a,
+                    format!("duplicate #[{}] attribute", T::ATTR),
+                ));
+            }
+
+            bare = true;
+        }
+
+        if attr.is_none() {
+            *attr = Some(a.clone());
+        }
+    }
+
+    bare
+}
+
+/// Parses the options of every attribute for `T` in `attrs` into `options`.
+fn parse_options<T: Options>(attrs: &[syn::Attribute], options: &mut T, errors: &mut Errors) {
+    let mut keys = Vec::<String>::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(T::ATTR)) {
+        if attr.tokens.is_empty() {
+            continue;
+        }
+
+        let parser = |input: ParseStream| {
+            if !input.peek(syn::token::Paren) {
+                return Err(input.error(format!("expected `#[{0}]` or `#[{0}(...)]`", T::ATTR,)));
+            }
+
+            let content;
+            syn::parenthesized!(content in input);
+
+            if !input.is_empty() {
+                return Err(input.error(format!("unexpected token after `#[{}(...)]`", T::ATTR)));
+            }
+
+            while !content.is_empty() {
+                let key = content.parse::<syn::Ident>()?;
+                let name = key.to_string();
+
+                if !T::KEYS.contains(&name.as_str()) {
+                    return Err(unknown_option::<T>(&key));
+                }
+
+                if keys.contains(&name) {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("duplicate option `{}`", key),
+                    ));
+                }
+
+                options.parse_option(&key, &content)?;
+                keys.push(name);
+
+                if content.is_empty() {
+                    break;
+                }
+
+                content.parse::<syn::Token![,]>()?;
+            }
+
+            Ok(())
+        };
+
+        if let Err(err) = parser.parse2(attr.tokens.clone()) {
+            errors.push(err);
+        }
+    }
+}
+
+/// A path of fields, `a.b.0`.
+pub struct FieldPath {
+    /// The field the path starts at.
+    pub field: syn::Member,
+    /// The fields following `field`.
+    pub nested: Vec<syn::Member>,
+}
+
+impl Parse for FieldPath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut members = Vec::new();
+        parse_members(input, &mut members)?;
+
+        while input.peek(syn::Token![.]) {
+            input.parse::<syn::Token![.]>()?;
+            parse_members(input, &mut members)?;
+        }
+
+        let field = members.remove(0);
+        Ok(Self {
+            field,
+            nested: members,
+        })
+    }
+}
+
+/// Parses the next field of a path, or the next two if they're lexed as one float.
+fn parse_members(input: ParseStream, members: &mut Vec<syn::Member>) -> syn::Result<()> {
+    // `0.1` and `a.0.1` contain `0.1`, which is lexed as a float
+    if !input.peek(syn::LitFloat) {
+        members.push(input.parse()?);
+        return Ok(());
+    }
+
+    let lit = input.parse::<syn::LitFloat>()?;
+    let error = || syn::Error::new(lit.span(), "expected a field name or index");
+
+    if !lit.suffix().is_empty() {
+        return Err(error());
+    }
+
+    for index in lit.base10_digits().split('.') {
+        let index = index.parse::<u32>().map_err(|_| error())?;
+
+        members.push(syn::Member::Unnamed(syn::Index {
+            index,
+            span: lit.span(),
+        }));
+    }
+
+    Ok(())
+}
+
+/// Traits accepted by `borrow(...)`.
+pub const BORROW_TRAITS: &[&str] = &["Hash", "PartialEq", "Eq", "PartialOrd", "Ord"];
+
+/// Traits accepted by `fmt(...)`, all in `core::fmt`.
+pub const FMT_TRAITS: &[&str] = &[
+    "Display", "Debug", "LowerHex", "UpperHex", "LowerExp", "UpperExp", "Binary", "Octal",
+    "Pointer",
+];
+
+/// Traits accepted by `io(...)`, all in `std::io`.
+pub const IO_TRAITS: &[&str] = &["Read", "Write", "Seek", "BufRead"];
+
+/// Traits accepted by `ops(...)`, all in `core::ops` except `Sum` and `Product`.
+pub const OPS_TRAITS: &[&str] = &[
+    "Add",
+    "Sub",
+    "Mul",
+    "Div",
+    "Rem",
+    "BitAnd",
+    "BitOr",
+    "BitXor",
+    "Shl",
+    "Shr",
+    "Neg",
+    "Not",
+    "AddAssign",
+    "SubAssign",
+    "MulAssign",
+    "DivAssign",
+    "RemAssign",
+    "BitAndAssign",
+    "BitOrAssign",
+    "BitXorAssign",
+    "ShlAssign",
+    "ShrAssign",
+    "Sum",
+    "Product",
+];
+
+/// Traits accepted by `cmp(...)`.
+pub const CMP_TRAITS: &[&str] = &["PartialEq", "PartialOrd"];
+
+/// The `error` option, `error` or `error = "context"`.
+pub struct ErrorOption {
+    pub key: syn::Ident,
+    /// Prefixed to the message of the target.
+    pub context: Option<syn::LitStr>,
+}
+
+impl ErrorOption {
+    fn parse(key: &syn::Ident, input: ParseStream) -> syn::Result<Self> {
+        let context = if input.peek(syn::Token![=]) {
+            Some(value(key, input)?)
+        } else {
+            flag(key, input)?;
+            None
+        };
+
+        Ok(Self {
+            key: key.clone(),
+            context,
+        })
+    }
+}
+
+/// Parses an option without a value.
+fn flag(key: &syn::Ident, input: ParseStream) -> syn::Result<syn::Ident> {
+    if input.is_empty() || input.peek(syn::Token![,]) {
+        Ok(key.clone())
+    } else {
+        Err(input.error(format!("option `{}` takes no value", key)))
+    }
+}
+
+/// Parses the value of an option, `key = value`.
+fn value<T: Parse>(key: &syn::Ident, input: ParseStream) -> syn::Result<T> {
+    if !input.peek(syn::Token![=]) {
+        return Err(input.error(format!("expected `{} = ...`", key)));
+    }
+
+    input.parse::<syn::Token![=]>()?;
+    input.parse()
+}
+
+/// Parses an expression value, `key = expr`, up to the next `,` outside of groups
+/// and turbofish generics.
+///
+/// The expression is checked by the compiler where it's used, parsing it would
+/// need the `full` feature of `syn`.
+fn expr(key: &syn::Ident, input: ParseStream) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.peek(syn::Token![=]) {
+        return Err(input.error(format!("expected `{} = ...`", key)));
+    }
+
+    input.parse::<syn::Token![=]>()?;
+
+    input.step(|cursor| {
+        let mut rest = *cursor;
+        let mut tokens = proc_macro2::TokenStream::new();
+        // open `<` of `::<...>` or of a leading `<T as Trait>`, and the ones nested in them
+        let mut generics = 0usize;
+        let mut last = None;
+
+        while let Some((tt, next)) = rest.token_tree() {
+            let punct = match tt {
+                proc_macro2::TokenTree::Punct(ref punct) => Some(punct.as_char()),
+                _ => None,
+            };
+
+            match (punct, last) {
+                (Some(','), _) if generics == 0 => break,
+                (Some('<'), _) if generics > 0 || last == Some(':') || tokens.is_empty() => {
+                    generics += 1
+                }
+                // the `->` of `Fn() -> T`
+                (Some('>'), Some('-')) => {}
+                (Some('>'), _) if generics > 0 => generics -= 1,
+                _ => {}
+            }
+
+            last = punct;
+            tokens.extend([tt]);
+            rest = next;
+        }
+
+        if tokens.is_empty() {
+            return Err(cursor.error(format!("expected an expression after `{} =`", key)));
+        }
+
+        Ok((tokens, rest))
+    })
+}
+
+/// Parses a list option, `key(a, b, c)`, where every item must be one of `items`.
+fn list(key: &syn::Ident, input: ParseStream, items: &[&str]) -> syn::Result<Vec<syn::Ident>> {
+    if !input.peek(syn::token::Paren) {
+        return Err(input.error(format!("expected `{}(...)`", key)));
+    }
+
+    let content;
+    syn::parenthesized!(content in input);
+
+    let idents = content.parse_terminated::<_, syn::Token![,]>(syn::Ident::parse)?;
+    let mut list = Vec::<syn::Ident>::new();
+
+    for ident in idents {
+        if !items.iter().any(|item| ident == item) {
+            let items = items.iter().map(|item| format!("`{}`", item));
+
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "unknown item `{}` in `{}`, expected one of: {}",
+                    ident,
+                    key,
+                    items.collect::<Vec<_>>().join(", "),
+                ),
+            ));
+        }
+
+        if list.contains(&ident) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("duplicate item `{}` in `{}`", ident, key),
+            ));
+        }
+
+        list.push(ident);
+    }
+
+    Ok(list)
+}
+
+fn unknown_option<T: Options>(key: &syn::Ident) -> syn::Error {
+    let message = if T::KEYS.is_empty() {
+        format!(
+            "unknown option `{}`, #[{}] on {} takes no options",
+            key,
+            T::ATTR,
+            T::POSITION,
+        )
+    } else {
+        let keys = T::KEYS.iter().map(|key| format!("`{}`", key));
+        format!(
+            "unknown option `{}`, expected one of: {}",
+            key,
+            keys.collect::<Vec<_>>().join(", "),
+        )
+    };
+
+    syn::Error::new(key.span(), message)
+}
@@ -0,0 +1,119 @@
+//! Expansion of `#[derive(Borrow)]` and `#[derive(BorrowMut)]`.
+
+use crate::DerefTarget;
+
+/// Expands `Borrow`, or `BorrowMut` if `mutable`.
+///
+/// `Borrow` also forwards the traits listed in `#[deref(borrow(...))]` to the
+/// target, so they agree with the target's as `Borrow` requires.
+pub fn expand(input: &syn::DeriveInput, mutable: bool) -> syn::Result<proc_macro2::TokenStream> {
+    let target = DerefTarget::get(input)?;
+    let required = target.require_derives(input, Some("Borrow"));
+
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref(mutable);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if mutable {
+        return Ok(quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::borrow::BorrowMut<#target_ty> for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn borrow_mut(&mut self) -> &mut #target_ty {
+                    #target_ref
+                }
+            }
+
+            #required
+        });
+    }
+
+    let traits = target.options.borrow.iter().map(|ident| {
+        let trait_ = ident.to_string();
+        forward_trait(input, &target, &trait_)
+    });
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::borrow::Borrow<#target_ty> for #ident #ty_generics #where_clause {
+            #[inline(always)]
+            fn borrow(&self) -> &#target_ty {
+                #target_ref
+            }
+        }
+
+        #(#traits)*
+        #required
+    })
+}
+
+/// Implements `trait_` for the type by comparing or hashing only the target.
+fn forward_trait(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+    trait_: &str,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let this = target.target_ref(false);
+    let other = target.target_ref_of(&quote::quote!(other), false);
+
+    let (trait_path, body) = match trait_ {
+        "Hash" => (
+            quote::quote!(::core::hash::Hash),
+            quote::quote! {
+                #[inline]
+                fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                    <#target_ty as ::core::hash::Hash>::hash(#this, state)
+                }
+            },
+        ),
+        "PartialEq" => (
+            quote::quote!(::core::cmp::PartialEq),
+            quote::quote! {
+                #[inline]
+                fn eq(&self, other: &Self) -> bool {
+                    <#target_ty as ::core::cmp::PartialEq>::eq(#this, #other)
+                }
+            },
+        ),
+        "Eq" => (quote::quote!(::core::cmp::Eq), quote::quote!()),
+        "PartialOrd" => (
+            quote::quote!(::core::cmp::PartialOrd),
+            quote::quote! {
+                #[inline]
+                fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                    <#target_ty as ::core::cmp::PartialOrd>::partial_cmp(#this, #other)
+                }
+            },
+        ),
+        "Ord" => (
+            quote::quote!(::core::cmp::Ord),
+            quote::quote! {
+                #[inline]
+                fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                    <#target_ty as ::core::cmp::Ord>::cmp(#this, #other)
+                }
+            },
+        ),
+        _ => unreachable!("unknown trait `{}`", trait_),
+    };
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#target_ty: #trait_path));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+            #body
+        }
+    }
+}
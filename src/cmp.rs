@@ -0,0 +1,153 @@
+//! Expansion of `#[deref(cmp(...))]`.
+
+use crate::{target, DerefTarget};
+
+/// Expands the traits listed in `#[deref(cmp(...))]` between the type and its
+/// target, in both directions.
+///
+/// A `String` target is also compared with `str` and `&str`, a `str` target with
+/// `&str`. Impls for a target that is a type parameter, `impl<T> PartialEq<W<T>>
+/// for T`, also behind `&`, `Box` or `Pin`, aren't allowed by the orphan rules and
+/// are skipped, like the ones for the `<F as Deref>::Target` of `forward`.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let target_ty = target.target_ty();
+
+    let mut others = vec![Other {
+        ty: quote::quote!(#target_ty),
+        compared: quote::quote!(#target_ty),
+        by_ref: false,
+    }];
+
+    let name = match target_ty {
+        syn::Type::Path(ref ty) if ty.qself.is_none() => ty.path.segments.last(),
+        _ => None,
+    };
+
+    match name.map(|segment| segment.ident.to_string()).as_deref() {
+        Some("String") => others.extend([Other::str(), Other::str_ref()]),
+        Some("str") => others.push(Other::str_ref()),
+        _ => {}
+    }
+
+    // a projection may be any type, the reverse impls could overlap
+    let is_projection = matches!(target_ty, syn::Type::Path(ref ty) if ty.qself.is_some());
+    let reverse = !is_projection && !target::is_uncovered(&target_ty, &input.generics);
+
+    let impls = target.options.cmp.iter().flat_map(|trait_| {
+        others.iter().map(move |other| {
+            let forward = impl_cmp(input, target, trait_, other, false);
+            let reverse = reverse.then(|| impl_cmp(input, target, trait_, other, true));
+
+            quote::quote!(#forward #reverse)
+        })
+    });
+
+    Ok(quote::quote!(#(#impls)*))
+}
+
+/// A type the type is compared with.
+struct Other {
+    ty: proc_macro2::TokenStream,
+    /// The type both sides are compared as.
+    compared: proc_macro2::TokenStream,
+    /// Whether `ty` is a reference to `compared`, `'__a` is the lifetime.
+    by_ref: bool,
+}
+
+impl Other {
+    fn str() -> Self {
+        Self {
+            ty: quote::quote!(str),
+            compared: quote::quote!(str),
+            by_ref: false,
+        }
+    }
+
+    fn str_ref() -> Self {
+        Self {
+            ty: quote::quote!(&'__a str),
+            compared: quote::quote!(str),
+            by_ref: true,
+        }
+    }
+}
+
+/// Implements `trait_` for the type with `other` on the right, or for `other` with
+/// the type on the right if `reverse`.
+fn impl_cmp(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+    trait_: &syn::Ident,
+    other: &Other,
+    reverse: bool,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let other_ty = &other.ty;
+    let compared = &other.compared;
+    let trait_path = quote::quote!(::core::cmp::#trait_);
+
+    let other_ref = match other.by_ref {
+        true => quote::quote!(*other),
+        false => quote::quote!(other),
+    };
+    let self_ref = match other.by_ref {
+        true => quote::quote!(*self),
+        false => quote::quote!(self),
+    };
+
+    let mut generics = input.generics.clone();
+    if other.by_ref {
+        generics.params.insert(0, syn::parse_quote!('__a));
+    }
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#compared: #trait_path));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let (self_ty, rhs_ty, lhs, rhs) = if reverse {
+        let rhs = target.target_ref_of(&quote::quote!(other), false);
+        (
+            quote::quote!(#other_ty),
+            quote::quote!(#ident #ty_generics),
+            self_ref,
+            rhs,
+        )
+    } else {
+        let lhs = target.target_ref(false);
+        (
+            quote::quote!(#ident #ty_generics),
+            quote::quote!(#other_ty),
+            lhs,
+            other_ref,
+        )
+    };
+
+    let method = match trait_.to_string().as_str() {
+        "PartialEq" => quote::quote! {
+            #[inline]
+            fn eq(&self, other: &#rhs_ty) -> bool {
+                <#compared as ::core::cmp::PartialEq>::eq(#lhs, #rhs)
+            }
+        },
+        "PartialOrd" => quote::quote! {
+            #[inline]
+            fn partial_cmp(&self, other: &#rhs_ty) -> ::core::option::Option<::core::cmp::Ordering> {
+                <#compared as ::core::cmp::PartialOrd>::partial_cmp(#lhs, #rhs)
+            }
+        },
+        _ => unreachable!("unknown trait `{}`", trait_),
+    };
+
+    quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_path<#rhs_ty> for #self_ty #where_clause {
+            #method
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Expansion of `#[deref(error)]`.
+
+use crate::{DerefTarget, Errors};
+
+/// Expands `Error`, `Display` and `Debug` with `#[deref(error)]`, making the type
+/// a transparent wrapper of the error in the target.
+///
+/// `source` is always the target's own source. With a context the target's message
+/// is already part of `Display`, so returning the target itself would repeat it
+/// when the chain of sources is printed.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let error = match target.options.error {
+        Some(ref error) => error,
+        None => return Ok(proc_macro2::TokenStream::new()),
+    };
+
+    // `core::error::Error` is only stable since Rust 1.81
+    if !cfg!(feature = "std") {
+        return Err(syn::Error::new(
+            error.key.span(),
+            "`error` requires the `std` feature of `deref-derive`",
+        ));
+    }
+
+    let mut errors = Errors::default();
+
+    for trait_ in target.options.fmt.iter() {
+        if trait_ == "Display" || trait_ == "Debug" {
+            errors.push(syn::Error::new(
+                trait_.span(),
+                format!("`{}` is already implemented by `{}`", trait_, error.key),
+            ));
+        }
+    }
+
+    errors.finish()?;
+
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref(false);
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#target_ty: ::std::error::Error));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let display = match error.context {
+        Some(ref context) => quote::quote! {
+            ::core::write!(f, "{}: {}", #context, target)
+        },
+        None => quote::quote! {
+            <#target_ty as ::core::fmt::Display>::fmt(target, f)
+        },
+    };
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::std::error::Error for #ident #ty_generics #where_clause {
+            #[inline]
+            fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                <#target_ty as ::std::error::Error>::source(#target_ref)
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::core::fmt::Display for #ident #ty_generics #where_clause {
+            #[inline]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                let target: &#target_ty = #target_ref;
+                #display
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::core::fmt::Debug for #ident #ty_generics #where_clause {
+            #[inline]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                <#target_ty as ::core::fmt::Debug>::fmt(#target_ref, f)
+            }
+        }
+    })
+}
@@ -0,0 +1,39 @@
+//! Expansion of `#[deref(fmt(...))]`.
+
+use crate::DerefTarget;
+
+/// Expands the formatting traits listed in `#[deref(fmt(...))]`, formatting only
+/// the target and ignoring every other field.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref(false);
+
+    let impls = target.options.fmt.iter().map(|trait_| {
+        let trait_path = quote::quote!(::core::fmt::#trait_);
+
+        let mut generics = input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#target_ty: #trait_path));
+
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+                #[inline]
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    <#target_ty as #trait_path>::fmt(#target_ref, f)
+                }
+            }
+        }
+    });
+
+    Ok(quote::quote!(#(#impls)*))
+}
@@ -0,0 +1,78 @@
+//! Expansion of `#[deref(from)]`.
+
+use syn::spanned::Spanned;
+
+use crate::{attr, target, DerefTarget, Errors};
+
+/// Expands `From<FieldTy>` if the type has `#[deref(from)]`.
+///
+/// The value is moved into the target field, see [`construct`].
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let from = match target.options.from {
+        Some(ref from) => from,
+        None => return Ok(proc_macro2::TokenStream::new()),
+    };
+
+    let (member, fields) = match (target.struct_member(), &input.data) {
+        (Some(member), syn::Data::Struct(data)) => (member, &data.fields),
+        _ => {
+            return Err(syn::Error::new(
+                from.span(),
+                "`from` is only supported on structs without a nested `path`",
+            ));
+        }
+    };
+
+    let construct = construct(fields, member, &quote::quote!(value));
+
+    let ident = &input.ident;
+    let field_ty = &target.field_ty;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::convert::From<#field_ty> for #ident #ty_generics #where_clause {
+            #[inline]
+            fn from(value: #field_ty) -> Self {
+                #construct
+            }
+        }
+    })
+}
+
+/// Returns an expression constructing `Self` with `value` in the target field
+/// `member`, and every other field initialized with its `#[deref(default = expr)]`
+/// or with `Default::default()`.
+pub fn construct(
+    fields: &syn::Fields,
+    member: &syn::Member,
+    value: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let inits = fields.iter().enumerate().map(|(i, field)| {
+        let field_member = target::member(i, field);
+
+        if field_member == *member {
+            return quote::quote!(#member: #value);
+        }
+
+        // errors were already reported when selecting the target
+        let options = attr::FieldOptions::parse(&field.attrs, &mut Errors::default());
+
+        match options.default {
+            Some(default) => quote::quote!(#field_member: #default),
+            None => {
+                let ty = &field.ty;
+                quote::quote_spanned!(ty.span()=> #field_member: <#ty as ::core::default::Default>::default())
+            }
+        }
+    });
+
+    quote::quote! {
+        Self {
+            #(#inits,)*
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Expansion of `#[derive(Index)]` and `#[derive(IndexMut)]`.
+
+use crate::DerefTarget;
+
+/// Expands `Index`, or `IndexMut` if `mutable`.
+///
+/// The impls are generic over the index and forward to the target, for every
+/// index the target can be indexed with. With `#[deref(index = Type)]` only
+/// `Type` is accepted, and `Index` also adds the typed `push` and
+/// `iter_enumerated` methods to a `Vec` target.
+pub fn expand(input: &syn::DeriveInput, mutable: bool) -> syn::Result<proc_macro2::TokenStream> {
+    let target = DerefTarget::get(input)?;
+    let required = target.require_derives(input, Some("Index"));
+
+    if let Some(ref index) = target.options.index {
+        let impls = expand_typed(input, &target, index, mutable);
+        return Ok(quote::quote!(#impls #required));
+    }
+
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref(mutable);
+
+    let trait_ = if mutable {
+        quote::quote!(::core::ops::IndexMut)
+    } else {
+        quote::quote!(::core::ops::Index)
+    };
+
+    let mut generics = input.generics.clone();
+    generics.params.push(syn::parse_quote!(__Index));
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#target_ty: #trait_<__Index>));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    if mutable {
+        return Ok(quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::IndexMut<__Index> for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn index_mut(&mut self, index: __Index) -> &mut Self::Output {
+                    <#target_ty as ::core::ops::IndexMut<__Index>>::index_mut(#target_ref, index)
+                }
+            }
+
+            #required
+        });
+    }
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::ops::Index<__Index> for #ident #ty_generics #where_clause {
+            type Output = <#target_ty as ::core::ops::Index<__Index>>::Output;
+
+            #[inline(always)]
+            fn index(&self, index: __Index) -> &Self::Output {
+                <#target_ty as ::core::ops::Index<__Index>>::index(#target_ref, index)
+            }
+        }
+
+        #required
+    })
+}
+
+/// Expands `Index<index>`, or `IndexMut<index>` if `mutable`, converting the
+/// index with `Into<usize>`.
+///
+/// For a `Vec` target `push` and `iter_enumerated` are added, converting
+/// positions back with `From<usize>`.
+fn expand_typed(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+    index: &syn::Type,
+    mutable: bool,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref(mutable);
+    let item_ty = quote::quote!(<#target_ty as ::core::ops::Index<usize>>::Output);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if mutable {
+        return quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::IndexMut<#index> for #ident #ty_generics #where_clause {
+                #[inline(always)]
+                fn index_mut(&mut self, index: #index) -> &mut Self::Output {
+                    let index: usize = ::core::convert::Into::into(index);
+                    <#target_ty as ::core::ops::IndexMut<usize>>::index_mut(#target_ref, index)
+                }
+            }
+        };
+    }
+
+    let impl_index = quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::ops::Index<#index> for #ident #ty_generics #where_clause {
+            type Output = #item_ty;
+
+            #[inline(always)]
+            fn index(&self, index: #index) -> &Self::Output {
+                let index: usize = ::core::convert::Into::into(index);
+                <#target_ty as ::core::ops::Index<usize>>::index(#target_ref, index)
+            }
+        }
+    };
+
+    let name = match target_ty {
+        syn::Type::Path(ref ty) if ty.qself.is_none() => ty.path.segments.last(),
+        _ => None,
+    };
+    let is_vec = matches!(name, Some(segment) if segment.ident == "Vec");
+
+    if !is_vec {
+        return impl_index;
+    }
+
+    let target_mut = target.target_ref(true);
+
+    quote::quote! {
+        #impl_index
+
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Appends `value` to the target, returning its index.
+            #[inline]
+            #vis fn push(&mut self, value: #item_ty) -> #index {
+                let target: &mut #target_ty = #target_mut;
+                let index = target.len();
+                target.push(value);
+                <#index as ::core::convert::From<usize>>::from(index)
+            }
+
+            /// Returns an iterator over the items of the target with their indices.
+            #[inline]
+            #vis fn iter_enumerated(
+                &self,
+            ) -> impl ::core::iter::Iterator<Item = (#index, &#item_ty)> + '_ {
+                let target: &#target_ty = #target_ref;
+                target
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| (<#index as ::core::convert::From<usize>>::from(index), value))
+            }
+        }
+    }
+}
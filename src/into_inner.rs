@@ -0,0 +1,97 @@
+//! Expansion of `#[deref(into_inner)]` and `#[deref(into_parts)]`.
+
+use crate::{target, DerefTarget, Errors};
+
+/// Expands `into_inner` and `From<Self>` for the type of the target field with
+/// `#[deref(into_inner)]`, and `into_parts` with `#[deref(into_parts)]`.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut errors = Errors::default();
+    let mut methods = Vec::new();
+    let mut from = None;
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let field_ty = &target.field_ty;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if let Some(ref into_inner) = target.options.into_inner {
+        if target.is_nested() {
+            errors.push(syn::Error::new(
+                into_inner.span(),
+                "`into_inner` can't be combined with a nested `path`",
+            ));
+        }
+
+        let field = target.field_of(&quote::quote!(self));
+
+        methods.push(quote::quote! {
+            /// Returns the target field, dropping every other field.
+            #[inline]
+            #vis fn into_inner(self) -> #field_ty {
+                #field
+            }
+        });
+
+        // `impl<T> From<Wrapper<T>> for T`, or for `Box<T>` and `&T`, isn't
+        // allowed by the orphan rules
+        if !target::is_uncovered(field_ty, &input.generics) {
+            from = Some(quote::quote! {
+                #[automatically_derived]
+                impl #impl_generics ::core::convert::From<#ident #ty_generics> for #field_ty #where_clause {
+                    #[inline]
+                    fn from(value: #ident #ty_generics) -> Self {
+                        value.into_inner()
+                    }
+                }
+            });
+        }
+    }
+
+    if let Some(ref into_parts) = target.options.into_parts {
+        match (target.struct_member(), &input.data) {
+            (Some(member), syn::Data::Struct(data)) => {
+                let others = data
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| (target::member(i, field), &field.ty))
+                    .filter(|(other, _)| other != member)
+                    .collect::<Vec<_>>();
+
+                let members = others.iter().map(|(member, _)| member);
+                let tys = others.iter().map(|(_, ty)| ty);
+
+                methods.push(quote::quote! {
+                    /// Returns the target field followed by every other field, in
+                    /// declaration order.
+                    #[inline]
+                    #vis fn into_parts(self) -> (#field_ty, #(#tys,)*) {
+                        (self.#member, #(self.#members,)*)
+                    }
+                });
+            }
+            _ => errors.push(syn::Error::new(
+                into_parts.span(),
+                "`into_parts` is only supported on structs without a nested `path`",
+            )),
+        }
+    }
+
+    errors.finish()?;
+
+    if methods.is_empty() {
+        return Ok(proc_macro2::TokenStream::new());
+    }
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#methods)*
+        }
+
+        #from
+    })
+}
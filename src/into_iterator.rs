@@ -0,0 +1,89 @@
+//! Expansion of `#[derive(IntoIterator)]`.
+
+use crate::DerefTarget;
+
+/// Expands `IntoIterator` for the type, and for shared and mutable references to it.
+///
+/// The owned impl moves the target field out, so it's only generated when the
+/// target is the field itself. The impls for references iterate over the target.
+pub fn expand(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let target = DerefTarget::get(input)?;
+
+    let owned = target.is_field().then(|| impl_owned(input, &target));
+    let shared = impl_ref(input, &target, false);
+    let mutable = impl_ref(input, &target, true);
+    let required = target.require_derives(input, None);
+
+    Ok(quote::quote! {
+        #owned
+        #shared
+        #mutable
+        #required
+    })
+}
+
+/// Implements `IntoIterator` for the type by moving the target field out.
+fn impl_owned(input: &syn::DeriveInput, target: &DerefTarget) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let field_ty = &target.field_ty;
+    let field = target.field_of(&quote::quote!(self));
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#field_ty: ::core::iter::IntoIterator));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::iter::IntoIterator for #ident #ty_generics #where_clause {
+            type Item = <#field_ty as ::core::iter::IntoIterator>::Item;
+            type IntoIter = <#field_ty as ::core::iter::IntoIterator>::IntoIter;
+
+            #[inline(always)]
+            fn into_iter(self) -> Self::IntoIter {
+                <#field_ty as ::core::iter::IntoIterator>::into_iter(#field)
+            }
+        }
+    }
+}
+
+/// Implements `IntoIterator` for `&Self`, or `&mut Self` if `mutable`, by
+/// iterating over a reference to the target.
+fn impl_ref(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+    mutable: bool,
+) -> proc_macro2::TokenStream {
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let target_ref = target.target_ref_of(&quote::quote!(self), mutable);
+    let mutability = mutable.then(<syn::Token![mut]>::default);
+    let ref_ty = quote::quote!(&'__a #mutability #target_ty);
+
+    let mut generics = input.generics.clone();
+    generics.params.insert(0, syn::parse_quote!('__a));
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#ref_ty: ::core::iter::IntoIterator));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics ::core::iter::IntoIterator for &'__a #mutability #ident #ty_generics #where_clause {
+            type Item = <#ref_ty as ::core::iter::IntoIterator>::Item;
+            type IntoIter = <#ref_ty as ::core::iter::IntoIterator>::IntoIter;
+
+            #[inline(always)]
+            fn into_iter(self) -> Self::IntoIter {
+                <#ref_ty as ::core::iter::IntoIterator>::into_iter(#target_ref)
+            }
+        }
+    }
+}
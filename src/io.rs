@@ -0,0 +1,154 @@
+//! Expansion of `#[deref(io(...))]`.
+
+use crate::DerefTarget;
+
+/// Expands the `std::io` traits listed in `#[deref(io(...))]`, forwarding every
+/// required method and the provided methods commonly overridden to the target.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let io = &target.options.io;
+
+    if let (Some(trait_), false) = (io.first(), cfg!(feature = "std")) {
+        return Err(syn::Error::new(
+            trait_.span(),
+            "`io` requires the `std` feature of `deref-derive`",
+        ));
+    }
+
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let this = target.target_ref(true);
+
+    let impls = io.iter().map(|trait_| {
+        let trait_path = quote::quote!(::std::io::#trait_);
+
+        let methods = match trait_.to_string().as_str() {
+            "Read" => quote::quote! {
+                #[inline]
+                fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Read>::read(#this, buf)
+                }
+
+                #[inline]
+                fn read_vectored(
+                    &mut self,
+                    bufs: &mut [::std::io::IoSliceMut<'_>],
+                ) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Read>::read_vectored(#this, bufs)
+                }
+
+                #[inline]
+                fn read_to_end(
+                    &mut self,
+                    buf: &mut ::std::vec::Vec<u8>,
+                ) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Read>::read_to_end(#this, buf)
+                }
+
+                #[inline]
+                fn read_to_string(
+                    &mut self,
+                    buf: &mut ::std::string::String,
+                ) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Read>::read_to_string(#this, buf)
+                }
+
+                #[inline]
+                fn read_exact(&mut self, buf: &mut [u8]) -> ::std::io::Result<()> {
+                    <#target_ty as ::std::io::Read>::read_exact(#this, buf)
+                }
+            },
+            "Write" => quote::quote! {
+                #[inline]
+                fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Write>::write(#this, buf)
+                }
+
+                #[inline]
+                fn write_vectored(
+                    &mut self,
+                    bufs: &[::std::io::IoSlice<'_>],
+                ) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::Write>::write_vectored(#this, bufs)
+                }
+
+                #[inline]
+                fn flush(&mut self) -> ::std::io::Result<()> {
+                    <#target_ty as ::std::io::Write>::flush(#this)
+                }
+
+                #[inline]
+                fn write_all(&mut self, buf: &[u8]) -> ::std::io::Result<()> {
+                    <#target_ty as ::std::io::Write>::write_all(#this, buf)
+                }
+
+                #[inline]
+                fn write_fmt(&mut self, args: ::std::fmt::Arguments<'_>) -> ::std::io::Result<()> {
+                    <#target_ty as ::std::io::Write>::write_fmt(#this, args)
+                }
+            },
+            "Seek" => quote::quote! {
+                #[inline]
+                fn seek(&mut self, pos: ::std::io::SeekFrom) -> ::std::io::Result<u64> {
+                    <#target_ty as ::std::io::Seek>::seek(#this, pos)
+                }
+
+                #[inline]
+                fn rewind(&mut self) -> ::std::io::Result<()> {
+                    <#target_ty as ::std::io::Seek>::rewind(#this)
+                }
+
+                #[inline]
+                fn stream_position(&mut self) -> ::std::io::Result<u64> {
+                    <#target_ty as ::std::io::Seek>::stream_position(#this)
+                }
+            },
+            "BufRead" => quote::quote! {
+                #[inline]
+                fn fill_buf(&mut self) -> ::std::io::Result<&[u8]> {
+                    <#target_ty as ::std::io::BufRead>::fill_buf(#this)
+                }
+
+                #[inline]
+                fn consume(&mut self, amt: usize) {
+                    <#target_ty as ::std::io::BufRead>::consume(#this, amt)
+                }
+
+                #[inline]
+                fn read_until(
+                    &mut self,
+                    byte: u8,
+                    buf: &mut ::std::vec::Vec<u8>,
+                ) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::BufRead>::read_until(#this, byte, buf)
+                }
+
+                #[inline]
+                fn read_line(&mut self, buf: &mut ::std::string::String) -> ::std::io::Result<usize> {
+                    <#target_ty as ::std::io::BufRead>::read_line(#this, buf)
+                }
+            },
+            _ => unreachable!("unknown trait `{}`", trait_),
+        };
+
+        let mut generics = input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#target_ty: #trait_path));
+
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+                #methods
+            }
+        }
+    });
+
+    Ok(quote::quote!(#(#impls)*))
+}
@@ -0,0 +1,84 @@
+//! Expansion of `#[derive(Iterator)]` and the other iterator traits.
+
+use crate::DerefTarget;
+
+/// Expands the iterator trait `trait_`, forwarding its methods to the target.
+///
+/// Only methods borrowing the iterator are forwarded, the ones consuming it
+/// use their default implementations.
+pub fn expand(input: &syn::DeriveInput, trait_: &str) -> syn::Result<proc_macro2::TokenStream> {
+    let target = DerefTarget::get(input)?;
+
+    let ident = &input.ident;
+    let target_ty = target.target_ty();
+    let this = target.target_ref(true);
+    let this_ref = target.target_ref(false);
+
+    let (trait_path, methods) = match trait_ {
+        "Iterator" => (
+            quote::quote!(::core::iter::Iterator),
+            quote::quote! {
+                type Item = <#target_ty as ::core::iter::Iterator>::Item;
+
+                #[inline]
+                fn next(&mut self) -> ::core::option::Option<Self::Item> {
+                    <#target_ty as ::core::iter::Iterator>::next(#this)
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, ::core::option::Option<usize>) {
+                    <#target_ty as ::core::iter::Iterator>::size_hint(#this_ref)
+                }
+
+                #[inline]
+                fn nth(&mut self, n: usize) -> ::core::option::Option<Self::Item> {
+                    <#target_ty as ::core::iter::Iterator>::nth(#this, n)
+                }
+            },
+        ),
+        "DoubleEndedIterator" => (
+            quote::quote!(::core::iter::DoubleEndedIterator),
+            quote::quote! {
+                #[inline]
+                fn next_back(&mut self) -> ::core::option::Option<Self::Item> {
+                    <#target_ty as ::core::iter::DoubleEndedIterator>::next_back(#this)
+                }
+
+                #[inline]
+                fn nth_back(&mut self, n: usize) -> ::core::option::Option<Self::Item> {
+                    <#target_ty as ::core::iter::DoubleEndedIterator>::nth_back(#this, n)
+                }
+            },
+        ),
+        "ExactSizeIterator" => (
+            quote::quote!(::core::iter::ExactSizeIterator),
+            quote::quote! {
+                #[inline]
+                fn len(&self) -> usize {
+                    <#target_ty as ::core::iter::ExactSizeIterator>::len(#this_ref)
+                }
+            },
+        ),
+        "FusedIterator" => (quote::quote!(::core::iter::FusedIterator), quote::quote!()),
+        _ => unreachable!("unknown trait `{}`", trait_),
+    };
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#target_ty: #trait_path));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let required = target.require_derives(input, None);
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+            #methods
+        }
+
+        #required
+    })
+}
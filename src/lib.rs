@@ -1,11 +1,42 @@
-//! A tiny crate that provides `#[derive(Deref)]` and `#[derive(DerefMut)]`.
+//! A tiny crate that provides `#[derive(Deref)]` and `#[derive(DerefMut)]`, for
+//! structs and enums.
 //!
 //! While this in unidiomatic to implement [`Deref`](std::ops::Deref) for wrapper types.
 //! It can be useful and sees widespread use in the community. Therefore, this crate
 //! provides a macro to derive [`Deref`](std::ops::Deref) and [`DerefMut`](std::ops::DerefMut)
 //! for you to help reduce boilerplate.
+//!
+//! The other traits of wrapper types forward to the same target field:
+//! [`AsRef`], [`AsMut`], [`Borrow`], [`BorrowMut`], [`Index`], [`IndexMut`],
+//! [`IntoIterator`], [`Iterator`], [`DoubleEndedIterator`], [`ExactSizeIterator`]
+//! and [`FusedIterator`]. Options on the type, like `#[deref(from, fmt(Display))]`,
+//! add conversions, formatting, errors, IO, pinning, operators and comparisons, see
+//! [`Deref`].
+//!
+//! The generated code only refers to `::core`, so the derives work in `#![no_std]` crates.
+//! The exceptions are `#[deref(io(...))]` and `#[deref(error)]`, which implement the
+//! `std::io` traits and `std::error::Error`, and are only available with the `std`
+//! feature, enabled by default.
+
+mod as_ref;
+mod attr;
+mod borrow;
+mod cmp;
+mod error;
+mod fmt;
+mod from;
+mod index;
+mod into_inner;
+mod into_iterator;
+mod io;
+mod iterator;
+mod ops;
+mod pin;
+mod target;
 
-/// Used to derive [`Deref`](std::ops::Deref) for a struct.
+use target::DerefTarget;
+
+/// Used to derive [`Deref`](std::ops::Deref) for a struct or an enum.
 ///
 /// # Example
 /// If have a struct with only one field, you can derive `Deref` for it.
@@ -30,6 +61,55 @@
 ///
 /// assert_eq!(*Foo::default(), 0);
 /// ```
+/// Fields of type `PhantomData<T>` or `PhantomPinned`, and fields marked with
+/// `#[deref(ignore)]`, aren't considered when the field is selected automatically.
+/// ```rust
+/// # use deref_derive::Deref;
+/// # use std::marker::PhantomData;
+/// #[derive(Deref)]
+/// struct Id<T>(u64, PhantomData<T>);
+///
+/// assert_eq!(*Id::<String>(42, PhantomData), 42);
+///
+/// #[derive(Default, Deref)]
+/// struct Cached {
+///     value: String,
+///     #[deref(ignore)]
+///     hits: u32,
+/// }
+///
+/// assert_eq!(Cached::default().len(), 0);
+/// ```
+/// `#[cfg]` and `#[cfg_attr]` on fields are evaluated before the derive sees the
+/// struct, so each configuration selects its own field.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// struct Word {
+///     #[cfg(target_pointer_width = "64")]
+///     value: u64,
+///     #[cfg(not(target_pointer_width = "64"))]
+///     value: u32,
+/// }
+///
+/// assert_eq!(*Word { value: 7 }, 7);
+///
+/// #[derive(Deref)]
+/// struct Timing {
+///     #[cfg_attr(debug_assertions, deref(ignore))]
+///     fast: u32,
+///     #[cfg_attr(not(debug_assertions), deref(ignore))]
+///     checked: u32,
+/// }
+///
+/// let timing = Timing { fast: 1, checked: 2 };
+///
+/// if cfg!(debug_assertions) {
+///     assert_eq!(*timing, timing.checked);
+/// } else {
+///     assert_eq!(*timing, timing.fast);
+/// }
+/// ```
 /// Tuple structs are also supported.
 /// ```rust
 /// # use deref_derive::{Deref, DerefMut};
@@ -41,123 +121,816 @@
 /// foo.push('!');
 ///
 /// assert_eq!(*foo, "bar!");
+/// ```
+/// The field can also be chosen on the type with `#[deref(field = ...)]`, by name
+/// or by index.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Default, Deref)]
+/// #[deref(field = name)]
+/// struct Foo {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// assert_eq!(Foo::default().len(), 0);
+///
+/// #[derive(Default, Deref)]
+/// #[deref(field = 1)]
+/// struct Bar(u32, String);
+///
+/// assert_eq!(Bar::default().len(), 0);
+/// ```
+/// With `#[deref(path = ...)]` the target is a nested field. The `Target` type must
+/// then be given with `target`, unless the path is a single field.
+/// ```rust
+/// # use deref_derive::{Deref, DerefMut};
+/// #[derive(Default)]
+/// struct Stream {
+///     buf: Vec<u8>,
+/// }
+///
+/// #[derive(Default)]
+/// struct Conn {
+///     stream: Stream,
+/// }
+///
+/// #[derive(Default, Deref, DerefMut)]
+/// #[deref(path = inner.stream.buf, target = Vec<u8>)]
+/// struct Session {
+///     id: u32,
+///     inner: Conn,
+/// }
+///
+/// let mut session = Session::default();
+/// session.push(42);
+///
+/// assert_eq!(session.inner.stream.buf, [42]);
+/// ```
+/// Paths into tuple structs work the same way.
+/// ```rust
+/// # use deref_derive::Deref;
+/// struct Pair(u8, u32);
+///
+/// #[derive(Deref)]
+/// #[deref(path = 0.1, target = u32)]
+/// struct Point(Pair);
+///
+/// assert_eq!(*Point(Pair(1, 2)), 2);
+/// ```
+/// Enums are supported when every variant holds a field of the same type,
+/// either as its only field or marked with `#[deref]`.
+/// ```rust
+/// # use deref_derive::{Deref, DerefMut};
+/// #[derive(Deref, DerefMut)]
+/// enum Node {
+///     Leaf(#[deref] String, u32),
+///     Branch { name: String },
+/// }
+///
+/// let mut node = Node::Leaf("leaf".to_string(), 0);
+/// node.push('!');
+///
+/// assert_eq!(*node, "leaf!");
+/// assert_eq!(Node::Branch { name: "branch".to_string() }.len(), 6);
+/// ```
+/// With `#[deref(forward)]`, on the field or on the type, the target is reached
+/// through the field's own `Deref`, which is useful for smart pointer newtypes.
+/// ```rust
+/// # use deref_derive::{Deref, DerefMut};
+/// # use std::sync::Arc;
+/// #[derive(Deref, DerefMut)]
+/// struct Buf(#[deref(forward)] Box<[u8]>);
+///
+/// let mut buf = Buf(vec![1, 2, 3].into_boxed_slice());
+/// buf[0] = 4;
+///
+/// assert_eq!(&*buf, &[4, 2, 3]);
+///
+/// #[derive(Deref)]
+/// #[deref(forward)]
+/// struct Handle(Arc<String>);
+///
+/// assert_eq!(Handle(Arc::new("handle".to_string())).len(), 6);
+/// ```
+/// A different `Target` can be chosen with `#[deref(target = Type)]`, the field is
+/// then coerced to it. This hides the API of owned containers, like `String::push`.
+/// ```rust
+/// # use deref_derive::{Deref, DerefMut};
+/// #[derive(Deref, DerefMut)]
+/// struct Name(#[deref(target = str)] String);
+///
+/// let mut name = Name("name".to_string());
+/// name.make_ascii_uppercase();
+///
+/// assert_eq!(&*name, "NAME");
+///
+/// #[derive(Deref)]
+/// #[deref(target = [T])]
+/// struct Items<T>(Vec<T>);
+///
+/// assert_eq!(Items(vec![1, 2, 3]).len(), 3);
+/// ```
+/// Coercing a pointer to a trait object, like `Box<dyn Trait>`, must also be
+/// `forward`, otherwise the pointer itself is expected to implement the trait.
+/// ```rust
+/// # use deref_derive::Deref;
+/// # use std::fmt::Display;
+/// #[derive(Deref)]
+/// #[deref(forward, target = dyn Display)]
+/// struct Message(Box<dyn Display + Send>);
+///
+/// assert_eq!(Message(Box::new(42)).to_string(), "42");
+/// ```
+/// `#[deref(from)]` on the type also implements `From` for the type of the target
+/// field. Every other field is initialized with `Default::default()`, or with the
+/// expression given in `#[deref(default = expr)]`, which also excludes the field
+/// from being selected.
+/// ```rust
+/// # use deref_derive::Deref;
+/// # use std::collections::HashMap;
+/// #[derive(Deref)]
+/// #[deref(from)]
+/// struct Cached {
+///     value: String,
+///     #[deref(default = usize::MAX)]
+///     len: usize,
+///     #[deref(default = "cache".to_string())]
+///     name: String,
+///     #[deref(default = HashMap::<String, u32>::with_capacity(4))]
+///     reads: HashMap<String, u32>,
+///     #[deref(ignore)]
+///     hits: u32,
+/// }
+///
+/// let cached = Cached::from("value".to_string());
+///
+/// assert_eq!(*cached, "value");
+/// assert_eq!(cached.len, usize::MAX);
+/// assert_eq!(cached.name, "cache");
+/// assert!(cached.reads.capacity() >= 4);
+/// assert_eq!(cached.hits, 0);
+/// ```
+/// `#[deref(into_inner)]` adds an `into_inner` method moving the target field out,
+/// and implements `From<Self>` for its type. `#[deref(into_parts)]` adds an
+/// `into_parts` method returning the target field followed by every other field.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// #[deref(into_inner, into_parts)]
+/// struct Counted {
+///     #[deref]
+///     value: String,
+///     reads: u32,
+/// }
+///
+/// let counted = || Counted { value: "value".to_string(), reads: 2 };
+///
+/// assert_eq!(counted().into_inner(), "value");
+/// assert_eq!(String::from(counted()), "value");
+/// assert_eq!(counted().into_parts(), ("value".to_string(), 2));
+/// ```
+/// The orphan rules don't allow `From<Self>` for a type parameter, also behind a
+/// reference, `Box` or `Pin`, so only `into_inner` is added then.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// #[deref(into_inner)]
+/// struct Boxed<T>(Box<T>);
+///
+/// #[derive(Deref)]
+/// #[deref(into_inner)]
+/// struct Borrowed<'a, T>(&'a T);
+///
+/// assert_eq!(*Boxed(Box::new(1)).into_inner(), 1);
+/// assert_eq!(*Borrowed(&1).into_inner(), 1);
+/// ```
+/// `#[deref(fmt(...))]` implements the listed formatting traits by formatting only
+/// the target, so wrappers print exactly like it. `Display`, `Debug`, `LowerHex`,
+/// `UpperHex`, `LowerExp`, `UpperExp`, `Binary`, `Octal` and `Pointer` are supported.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// #[deref(fmt(Display, Debug, LowerHex))]
+/// struct UserId {
+///     #[deref]
+///     id: u32,
+///     cached: bool,
+/// }
+///
+/// let id = UserId { id: 255, cached: true };
+///
+/// assert_eq!(format!("{} {:?} {:#x}", id, id, id), "255 255 0xff");
+/// ```
+/// `#[deref(error)]` makes the type a transparent error, `Display`, `Debug` and
+/// `Error::source` are forwarded to the target. With `#[deref(error = "context")]`
+/// the message is prefixed with the context. It requires the `std` feature.
+///
+#[cfg_attr(
+    feature = "std",
+    doc = r#"```rust
+# use deref_derive::Deref;
+# use std::{error::Error, io};
+#[derive(Deref)]
+#[deref(error = "failed to read config")]
+struct ConfigError(io::Error);
+
+#[derive(Deref)]
+#[deref(error)]
+struct ReadError(io::Error);
+
+let error = ConfigError(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+assert_eq!(error.to_string(), "failed to read config: no such file");
+assert!(error.source().is_none());
+
+let error = ReadError(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+assert_eq!(error.to_string(), "no such file");
+```"#
+)]
+/// `#[deref(io(...))]` forwards the listed `std::io` traits to the target, `Read`,
+/// `Write`, `Seek` and `BufRead` are supported. It requires the `std` feature.
+///
+#[cfg_attr(
+    feature = "std",
+    doc = r#"```rust
+# use deref_derive::Deref;
+# use std::io::{self, BufRead, Read, Write};
+#[derive(Deref)]
+#[deref(io(Read, BufRead, Write))]
+struct Counted<S> {
+    #[deref]
+    inner: S,
+    bytes: u64,
+}
+
+let mut input = Counted { inner: io::Cursor::new(b"first\nsecond".to_vec()), bytes: 0 };
+let mut line = String::new();
+input.read_line(&mut line).unwrap();
+input.read_to_string(&mut line).unwrap();
+
+assert_eq!(line, "first\nsecond");
+
+let mut output = Counted { inner: Vec::new(), bytes: 0 };
+write!(output, "{}", 42).unwrap();
+
+assert_eq!(output.inner, b"42");
+```"#
+)]
+/// `#[deref(pin)]` pins the target field structurally and adds a `project` method
+/// returning it pinned, `#[deref(future)]` also implements `Future` by polling it.
+/// The type is then `Unpin` only if the target field is, and it must neither
+/// implement `Drop` nor be `#[repr(packed)]`.
+/// ```rust
+/// # use deref_derive::Deref;
+/// # use std::{future::Future, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+/// # fn noop_waker() -> Waker {
+/// #     fn clone(_: *const ()) -> RawWaker {
+/// #         RawWaker::new(std::ptr::null(), &VTABLE)
+/// #     }
+/// #     fn noop(_: *const ()) {}
+/// #     static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+/// #     unsafe { Waker::from_raw(clone(std::ptr::null())) }
+/// # }
+/// #[derive(Deref)]
+/// #[deref(pin, future)]
+/// struct Named<F> {
+///     #[deref]
+///     future: F,
+///     name: &'static str,
+/// }
+///
+/// let mut future = Box::pin(Named { future: async { 42 }, name: "answer" });
+/// let waker = noop_waker();
+/// let mut cx = Context::from_waker(&waker);
+///
+/// assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(42));
+/// ```
+/// `DerefMut` is only implemented if the target field is `Unpin`. The field must
+/// stay pinned structurally, so the type can't implement `Drop`, be
+/// `#[repr(packed)]` or implement `Unpin` itself.
+///
+/// `#[deref(ops(...))]` implements the listed operators on the target field. Binary
+/// operators and their `*Assign` variants accept both the type and the target on
+/// the right and keep the other fields of the left operand. `Neg`, `Not`, `Sum`
+/// and `Product` are supported as well, the last two initialize the other fields
+/// like `from` does.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Clone, Copy, Debug, PartialEq, Deref)]
+/// #[deref(ops(Add, Mul, Neg, AddAssign, Sum))]
+/// struct Meters {
+///     #[deref]
+///     value: f64,
+///     #[deref(default = 0)]
+///     precision: u8,
+/// }
+///
+/// let mut length = Meters { value: 2.0, precision: 1 };
+/// length += Meters { value: 1.0, precision: 2 };
+///
+/// assert_eq!(length * 2.0, Meters { value: 6.0, precision: 1 });
+/// assert_eq!(-length + 1.0, Meters { value: -2.0, precision: 1 });
+/// assert_eq!([length, length].into_iter().sum::<Meters>(), Meters { value: 6.0, precision: 0 });
+/// ```
+/// `#[deref(cmp(PartialEq, PartialOrd))]` compares the type with its target, in both
+/// directions. A `String` target is also compared with `str` and `&str`, a `str`
+/// target with `&str`.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// #[deref(cmp(PartialEq, PartialOrd))]
+/// struct UserId(String);
+///
+/// let id = UserId("alice".to_string());
+///
+/// assert!(id == "alice");
+/// assert!("alice" == id);
+/// assert!(id == "alice".to_string());
+/// assert!(id < "bob");
+/// ```
+/// Only the impls with the type on the left are added for a type parameter, also
+/// behind `&`, `Box` or `Pin`, and for the `Target` of a `forward` field, as the
+/// orphan rules or overlapping impls rule the others out.
+/// ```rust
+/// # use deref_derive::Deref;
+/// #[derive(Deref)]
+/// #[deref(cmp(PartialEq))]
+/// struct Boxed<T>(Box<T>);
+///
+/// #[derive(Deref)]
+/// #[deref(forward, cmp(PartialEq, PartialOrd))]
+/// struct Name(Box<String>);
+///
+/// assert!(Boxed(Box::new(1)) == Box::new(1));
+/// assert!(Name(Box::new("alice".to_string())) < "bob".to_string());
+/// ```
+/// `PartialOrd` needs `PartialEq` with the same type, so it isn't accepted alone.
+///
+/// # Errors
+/// The attribute is checked strictly, unknown or repeated options are rejected. If
+/// the field can't be determined, a compile error points at the offending fields,
+/// or lists the candidates when none is marked. Every variant of an enum needs a
+/// target field, all of the same type. `field = ...` must name an existing field,
+/// and can't be combined with a marked field.
+///
+/// Every option on the type adding items is expanded by a single derive, `borrow`
+/// by `Borrow`, `index` by `Index` and the others by `Deref`. The other derives
+/// reading the attribute require that derive, instead of ignoring the option.
 #[proc_macro_derive(Deref, attributes(deref))]
 pub fn derive_deref(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
-    let ident = input.ident;
 
-    let target = DerefTarget::get(&input.data);
-    let target_ty = target.ty;
-    let target_field = target.field;
+    let target = match DerefTarget::get(&input) {
+        Ok(target) => target,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    // items added by options on the type
+    let mut items = proc_macro2::TokenStream::new();
+    let mut errors = Errors::default();
+
+    for expand in [
+        from::expand,
+        into_inner::expand,
+        fmt::expand,
+        error::expand,
+        io::expand,
+        pin::expand,
+        ops::expand,
+        cmp::expand,
+    ] {
+        match expand(&input, &target) {
+            Ok(tokens) => items.extend(tokens),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if let Err(err) = errors.finish() {
+        return err.to_compile_error().into();
+    }
+
+    items.extend(target.require_derives(&input, Some("Deref")));
+
+    let ident = input.ident;
+    let target_ty = target.target_ty();
+    let target_field = target.target_ref(false);
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     let expanded = quote::quote! {
         #[automatically_derived]
-        impl #impl_generics ::std::ops::Deref for #ident #ty_generics #where_clause {
+        impl #impl_generics ::core::ops::Deref for #ident #ty_generics #where_clause {
             type Target = #target_ty;
 
             #[inline(always)]
             fn deref(&self) -> &Self::Target {
-                &self.#target_field
+                #target_field
             }
         }
+
+        #items
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
-/// Used to derive [`DerefMut`](std::ops::DerefMut) for a struct.
+/// Used to derive [`DerefMut`](std::ops::DerefMut) for a struct or an enum.
 ///
 /// For examples, see [`Deref`].
 #[proc_macro_derive(DerefMut, attributes(deref))]
 pub fn derive_deref_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    let target = match DerefTarget::get(&input) {
+        Ok(target) => target,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let required = target.require_derives(&input, Some("Deref"));
+
     let ident = input.ident;
+    let target_field = target.target_ref(true);
 
-    let target = DerefTarget::get(&input.data);
-    let target_field = target.field;
+    let mut generics = input.generics;
+    if let Some(bound) = target.unpin_bound() {
+        generics.make_where_clause().predicates.push(bound);
+    }
 
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let expanded = quote::quote! {
         #[automatically_derived]
-        impl #impl_generics ::std::ops::DerefMut for #ident #ty_generics #where_clause {
+        impl #impl_generics ::core::ops::DerefMut for #ident #ty_generics #where_clause {
             #[inline(always)]
             fn deref_mut(&mut self) -> &mut Self::Target {
-                &mut self.#target_field
+                #target_field
             }
         }
+
+        #required
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
-struct DerefTarget {
-    ty: syn::Type,
-    field: proc_macro2::TokenStream,
-    has_attr: bool,
+/// Used to derive [`AsRef`] for a struct or an enum.
+///
+/// # Example
+/// Without attributes, `AsRef` is implemented for the same field and `Target`
+/// as [`Deref`] would use.
+/// ```rust
+/// # use deref_derive::AsRef;
+/// #[derive(AsRef)]
+/// struct Name(#[deref(target = str)] String);
+///
+/// fn len(s: impl AsRef<str>) -> usize {
+///     s.as_ref().len()
+/// }
+///
+/// assert_eq!(len(Name("name".to_string())), 4);
+/// ```
+/// Several fields of different types can be marked with `#[as_ref]`, each of
+/// them gets an impl for its own type.
+/// ```rust
+/// # use deref_derive::{AsMut, AsRef};
+/// # #[derive(Default)]
+/// # struct Config;
+/// # #[derive(Default)]
+/// # struct Logger;
+/// #[derive(Default, AsRef, AsMut)]
+/// struct App {
+///     #[as_ref]
+///     config: Config,
+///     #[as_ref]
+///     logger: Logger,
+///     name: String,
+/// }
+///
+/// let mut app = App::default();
+/// let _: &Config = app.as_ref();
+/// let _: &mut Logger = app.as_mut();
+/// ```
+/// With `#[as_ref(forward)]`, `AsRef<U>` is implemented for every `U` the field
+/// implements `AsRef<U>` for.
+/// ```rust
+/// # use deref_derive::AsRef;
+/// # use std::{ffi::OsStr, path::Path};
+/// #[derive(AsRef)]
+/// struct Name(#[as_ref(forward)] String);
+///
+/// let name = Name("name".to_string());
+///
+/// let _: &str = name.as_ref();
+/// let _: &[u8] = name.as_ref();
+/// let _: &OsStr = name.as_ref();
+/// let _: &Path = name.as_ref();
+/// ```
+/// Two marked fields of the same type are rejected.
+#[proc_macro_derive(AsRef, attributes(as_ref, deref))]
+pub fn derive_as_ref(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match as_ref::expand(&input, false) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`AsMut`] for a struct or an enum.
+///
+/// For examples, see [`AsRef`].
+#[proc_macro_derive(AsMut, attributes(as_ref, deref))]
+pub fn derive_as_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match as_ref::expand(&input, true) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
 }
 
-impl DerefTarget {
-    const ATTR_NAME: &'static str = "deref";
+/// Used to derive [`Borrow`](std::borrow::Borrow) for a struct or an enum.
+///
+/// The type borrows as the same `Target` as [`Deref`] would use.
+///
+/// # Example
+/// `Borrow` requires `Hash`, `Eq` and `Ord` to agree with the target's. With
+/// `#[deref(borrow(...))]` on the type, the listed traits are implemented by
+/// comparing and hashing only the target, ignoring every other field. The
+/// supertraits of a listed trait must be listed too, `Ord` needs `Eq` and
+/// `PartialOrd`.
+/// ```rust
+/// # use deref_derive::Borrow;
+/// # use std::collections::HashMap;
+/// #[derive(Borrow)]
+/// #[deref(borrow(Hash, PartialEq, Eq))]
+/// struct Key {
+///     #[deref(target = str)]
+///     name: String,
+///     hits: u32,
+/// }
+///
+/// let mut map = HashMap::new();
+/// map.insert(Key { name: "key".to_string(), hits: 0 }, 42);
+///
+/// assert_eq!(map["key"], 42);
+/// assert!(Key { name: "key".to_string(), hits: 1 } == Key { name: "key".to_string(), hits: 2 });
+/// ```
+#[proc_macro_derive(Borrow, attributes(deref))]
+pub fn derive_borrow(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
 
-    fn has_attr(attrs: &[syn::Attribute]) -> bool {
-        attrs.iter().any(|attr| attr.path.is_ident(Self::ATTR_NAME))
+    match borrow::expand(&input, false) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
     }
+}
 
-    fn get_target(mut targets: impl ExactSizeIterator<Item = Self>) -> Self {
-        if targets.len() == 1 {
-            targets.next().unwrap()
-        } else {
-            let targets = targets.filter(|target| target.has_attr).collect::<Vec<_>>();
+/// Used to derive [`BorrowMut`](std::borrow::BorrowMut) for a struct or an enum.
+///
+/// For examples, see [`Borrow`].
+#[proc_macro_derive(BorrowMut, attributes(deref))]
+pub fn derive_borrow_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
 
-            if targets.len() == 1 {
-                targets.into_iter().next().unwrap()
-            } else {
-                panic!("expected exactly one field with #[deref] attribute");
-            }
+    match borrow::expand(&input, true) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`Index`](std::ops::Index) for a struct or an enum.
+///
+/// The type is indexed like the same `Target` as [`Deref`] would use, with every
+/// index type the target supports.
+///
+/// # Example
+/// ```rust
+/// # use deref_derive::{Index, IndexMut};
+/// #[derive(Index, IndexMut)]
+/// struct Row {
+///     #[deref]
+///     cells: Vec<u32>,
+///     dirty: bool,
+/// }
+///
+/// let mut row = Row { cells: vec![1, 2, 3], dirty: false };
+/// row[0] = 4;
+///
+/// assert_eq!(row[0], 4);
+/// assert_eq!(row[1..], [2, 3]);
+/// ```
+/// With `#[deref(index = Type)]` the type is only indexed with `Type`, converted
+/// with `Into<usize>`. For a `Vec` target, `push` returns the index of the new item
+/// and `iter_enumerated` yields the items with their indices, both converted back
+/// with `From<usize>`.
+/// ```rust
+/// # use deref_derive::{Index, IndexMut};
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct ItemId(u32);
+///
+/// impl From<ItemId> for usize {
+///     fn from(id: ItemId) -> usize {
+///         id.0 as usize
+///     }
+/// }
+///
+/// impl From<usize> for ItemId {
+///     fn from(index: usize) -> Self {
+///         ItemId(index as u32)
+///     }
+/// }
+///
+/// #[derive(Default, Index, IndexMut)]
+/// #[deref(index = ItemId)]
+/// struct Items(Vec<&'static str>);
+///
+/// let mut items = Items::default();
+/// let sword = items.push("sword");
+/// let shield = items.push("shield");
+/// items[sword] = "axe";
+///
+/// assert_eq!(items[shield], "shield");
+/// assert_eq!(
+///     items.iter_enumerated().collect::<Vec<_>>(),
+///     [(ItemId(0), &"axe"), (ItemId(1), &"shield")],
+/// );
+/// ```
+/// Other targets are only indexed, like a fixed size array.
+/// ```rust
+/// # use deref_derive::{Index, IndexMut};
+/// # #[derive(Clone, Copy)]
+/// # struct ItemId(u32);
+/// # impl From<ItemId> for usize {
+/// #     fn from(id: ItemId) -> usize {
+/// #         id.0 as usize
+/// #     }
+/// # }
+/// #[derive(Index, IndexMut)]
+/// #[deref(index = ItemId)]
+/// struct Slots([u32; 4]);
+///
+/// let mut slots = Slots([0; 4]);
+/// slots[ItemId(2)] = 7;
+///
+/// assert_eq!(slots[ItemId(2)], 7);
+/// assert_eq!(slots.0, [0, 0, 7, 0]);
+/// ```
+#[proc_macro_derive(Index, attributes(deref))]
+pub fn derive_index(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match index::expand(&input, false) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`IndexMut`](std::ops::IndexMut) for a struct or an enum.
+///
+/// For examples, see [`Index`].
+#[proc_macro_derive(IndexMut, attributes(deref))]
+pub fn derive_index_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match index::expand(&input, true) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`IntoIterator`] for a struct or an enum, and for shared and
+/// mutable references to it.
+///
+/// The references iterate over the same `Target` as [`Deref`] would use. The type
+/// itself iterates over the target field by value, only when the target is the
+/// field itself, without `forward`, `target = Type` or a nested `path`.
+///
+/// # Example
+/// ```rust
+/// # use deref_derive::IntoIterator;
+/// #[derive(IntoIterator)]
+/// struct Names {
+///     #[deref]
+///     names: Vec<String>,
+///     sorted: bool,
+/// }
+///
+/// let mut names = Names { names: vec!["a".to_string(), "b".to_string()], sorted: true };
+///
+/// for name in &mut names {
+///     name.push('!');
+/// }
+///
+/// assert_eq!((&names).into_iter().count(), 2);
+/// assert_eq!(names.into_iter().collect::<Vec<_>>(), ["a!", "b!"]);
+/// ```
+#[proc_macro_derive(IntoIterator, attributes(deref))]
+pub fn derive_into_iterator(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match into_iterator::expand(&input) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`Iterator`] for a struct or an enum.
+///
+/// The type iterates like the same `Target` as [`Deref`] would use, and is an
+/// iterator exactly when the target is. [`DoubleEndedIterator`](macro@DoubleEndedIterator),
+/// [`ExactSizeIterator`](macro@ExactSizeIterator) and [`FusedIterator`](macro@FusedIterator)
+/// are derived separately.
+///
+/// # Example
+/// ```rust
+/// # use deref_derive::{DoubleEndedIterator, ExactSizeIterator, FusedIterator, Iterator};
+/// #[derive(Iterator, DoubleEndedIterator, ExactSizeIterator, FusedIterator)]
+/// struct Chars<I> {
+///     #[deref]
+///     inner: I,
+///     source: &'static str,
+/// }
+///
+/// let mut chars = Chars { inner: vec!['a', 'b', 'c'].into_iter(), source: "abc" };
+///
+/// assert_eq!(chars.len(), 3);
+/// assert_eq!(chars.next_back(), Some('c'));
+/// assert_eq!(chars.collect::<String>(), "ab");
+/// ```
+#[proc_macro_derive(Iterator, attributes(deref))]
+pub fn derive_iterator(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match iterator::expand(&input, "Iterator") {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`DoubleEndedIterator`] for a struct or an enum.
+///
+/// For examples, see [`Iterator`](macro@Iterator).
+#[proc_macro_derive(DoubleEndedIterator, attributes(deref))]
+pub fn derive_double_ended_iterator(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match iterator::expand(&input, "DoubleEndedIterator") {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`ExactSizeIterator`] for a struct or an enum.
+///
+/// For examples, see [`Iterator`](macro@Iterator).
+#[proc_macro_derive(ExactSizeIterator, attributes(deref))]
+pub fn derive_exact_size_iterator(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match iterator::expand(&input, "ExactSizeIterator") {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Used to derive [`FusedIterator`](std::iter::FusedIterator) for a struct or an enum.
+///
+/// For examples, see [`Iterator`](macro@Iterator).
+#[proc_macro_derive(FusedIterator, attributes(deref))]
+pub fn derive_fused_iterator(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    match iterator::expand(&input, "FusedIterator") {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A list of errors that are reported together.
+#[derive(Default)]
+struct Errors {
+    error: Option<syn::Error>,
+}
+
+impl Errors {
+    fn push(&mut self, err: syn::Error) {
+        match self.error {
+            Some(ref mut error) => error.combine(err),
+            None => self.error = Some(err),
         }
     }
 
-    fn get(data: &syn::Data) -> Self {
-        match data {
-            syn::Data::Struct(data) => match data.fields {
-                syn::Fields::Named(ref fields) => {
-                    let fields = fields.named.iter().map(|f| {
-                        let ty = f.ty.clone();
-                        let field = f.ident.clone().unwrap();
-                        let has_attr = Self::has_attr(&f.attrs);
-
-                        Self {
-                            ty,
-                            field: quote::quote!(#field),
-                            has_attr,
-                        }
-                    });
-
-                    Self::get_target(fields)
-                }
-                syn::Fields::Unnamed(ref fields) => {
-                    let fields = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                        let ty = f.ty.clone();
-                        let field = syn::Index::from(i);
-                        let has_attr = Self::has_attr(&f.attrs);
-
-                        Self {
-                            ty,
-                            field: quote::quote!(#field),
-                            has_attr,
-                        }
-                    });
-
-                    Self::get_target(fields)
-                }
-                syn::Fields::Unit => {
-                    panic!("cannot be derived for unit structs")
-                }
-            },
-            _ => unimplemented!("can only be derived for structs"),
+    /// Takes all pushed errors combined, there must be at least one.
+    fn take(&mut self) -> syn::Error {
+        self.error.take().expect("no errors were pushed")
+    }
+
+    /// Returns all pushed errors combined, if there are any.
+    fn finish(self) -> syn::Result<()> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(()),
         }
     }
 }
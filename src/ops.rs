@@ -0,0 +1,217 @@
+//! Expansion of `#[deref(ops(...))]`.
+
+use crate::{from, DerefTarget};
+
+/// Expands the operators listed in `#[deref(ops(...))]` on the target field.
+///
+/// Binary operators and their `*Assign` variants are implemented both with `Self`
+/// and with the type of the target field on the right, the other fields are kept
+/// from the left operand. `Sum` and `Product` initialize the other fields like
+/// `#[deref(from)]` does.
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let ops = &target.options.ops;
+
+    let (member, fields) = match (ops.first(), target.struct_member(), &input.data) {
+        (None, _, _) => return Ok(proc_macro2::TokenStream::new()),
+        (Some(_), Some(member), syn::Data::Struct(data)) => (member, &data.fields),
+        (Some(op), _, _) => {
+            return Err(syn::Error::new(
+                op.span(),
+                "`ops` is only supported on structs without a nested `path`",
+            ));
+        }
+    };
+
+    let field_ty = &target.field_ty;
+    let this = Operands {
+        input,
+        member,
+        field_ty,
+    };
+
+    let impls = ops.iter().map(|op| {
+        let name = op.to_string();
+        let method = syn::Ident::new(&method_name(&name), op.span());
+
+        match name.as_str() {
+            "Neg" | "Not" => this.unary(op, &method),
+            "Sum" | "Product" => this.fold(op, &method, fields),
+            _ if name.ends_with("Assign") => {
+                let with_self = this.assign(op, &method, None);
+                let with_field = this.assign(op, &method, Some(field_ty));
+                quote::quote!(#with_self #with_field)
+            }
+            _ => {
+                let with_self = this.binary(op, &method, None);
+                let with_field = this.binary(op, &method, Some(field_ty));
+                quote::quote!(#with_self #with_field)
+            }
+        }
+    });
+
+    Ok(quote::quote!(#(#impls)*))
+}
+
+/// Returns the method of the operator trait `name`, `BitAndAssign` is `bitand_assign`.
+fn method_name(name: &str) -> String {
+    match name.strip_suffix("Assign") {
+        Some(op) => format!("{}_assign", op.to_lowercase()),
+        None => name.to_lowercase(),
+    }
+}
+
+/// The type and its target field the operators are implemented on.
+struct Operands<'a> {
+    input: &'a syn::DeriveInput,
+    member: &'a syn::Member,
+    field_ty: &'a syn::Type,
+}
+
+impl Operands<'_> {
+    /// Implements `trait_` for the type, with `bound` on the type of the field.
+    fn impl_trait(
+        &self,
+        trait_: proc_macro2::TokenStream,
+        bound: proc_macro2::TokenStream,
+        body: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let ident = &self.input.ident;
+        let field_ty = self.field_ty;
+
+        let mut generics = self.input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#field_ty: #bound));
+
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let (_, ty_generics, _) = self.input.generics.split_for_impl();
+
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics #trait_ for #ident #ty_generics #where_clause {
+                #body
+            }
+        }
+    }
+
+    /// Returns the type of the right operand, the type itself if `rhs` is `None`,
+    /// and the expression moving the value for the field out of `rhs`.
+    fn rhs(&self, rhs: Option<&syn::Type>) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        let member = self.member;
+
+        match rhs {
+            Some(ty) => (quote::quote!(#ty), quote::quote!(rhs)),
+            None => {
+                let ident = &self.input.ident;
+                let (_, ty_generics, _) = self.input.generics.split_for_impl();
+
+                (
+                    quote::quote!(#ident #ty_generics),
+                    quote::quote!(rhs.#member),
+                )
+            }
+        }
+    }
+
+    fn unary(&self, op: &syn::Ident, method: &syn::Ident) -> proc_macro2::TokenStream {
+        let member = self.member;
+        let field_ty = self.field_ty;
+        let trait_ = quote::quote!(::core::ops::#op);
+
+        self.impl_trait(
+            trait_.clone(),
+            quote::quote!(#trait_<Output = #field_ty>),
+            quote::quote! {
+                type Output = Self;
+
+                #[inline]
+                fn #method(mut self) -> Self {
+                    self.#member = <#field_ty as #trait_>::#method(self.#member);
+                    self
+                }
+            },
+        )
+    }
+
+    fn binary(
+        &self,
+        op: &syn::Ident,
+        method: &syn::Ident,
+        rhs: Option<&syn::Type>,
+    ) -> proc_macro2::TokenStream {
+        let member = self.member;
+        let field_ty = self.field_ty;
+        let (rhs_ty, rhs_value) = self.rhs(rhs);
+        let field_trait = quote::quote!(::core::ops::#op<#field_ty>);
+
+        self.impl_trait(
+            quote::quote!(::core::ops::#op<#rhs_ty>),
+            quote::quote!(::core::ops::#op<#field_ty, Output = #field_ty>),
+            quote::quote! {
+                type Output = Self;
+
+                #[inline]
+                fn #method(mut self, rhs: #rhs_ty) -> Self {
+                    self.#member = <#field_ty as #field_trait>::#method(self.#member, #rhs_value);
+                    self
+                }
+            },
+        )
+    }
+
+    fn assign(
+        &self,
+        op: &syn::Ident,
+        method: &syn::Ident,
+        rhs: Option<&syn::Type>,
+    ) -> proc_macro2::TokenStream {
+        let member = self.member;
+        let field_ty = self.field_ty;
+        let (rhs_ty, rhs_value) = self.rhs(rhs);
+        let field_trait = quote::quote!(::core::ops::#op<#field_ty>);
+
+        self.impl_trait(
+            quote::quote!(::core::ops::#op<#rhs_ty>),
+            field_trait.clone(),
+            quote::quote! {
+                #[inline]
+                fn #method(&mut self, rhs: #rhs_ty) {
+                    <#field_ty as #field_trait>::#method(&mut self.#member, #rhs_value)
+                }
+            },
+        )
+    }
+
+    fn fold(
+        &self,
+        op: &syn::Ident,
+        method: &syn::Ident,
+        fields: &syn::Fields,
+    ) -> proc_macro2::TokenStream {
+        let member = self.member;
+        let field_ty = self.field_ty;
+        let trait_ = quote::quote!(::core::iter::#op);
+
+        let value = quote::quote! {
+            <#field_ty as #trait_>::#method(
+                ::core::iter::Iterator::map(iter, |value| value.#member),
+            )
+        };
+        let construct = from::construct(fields, member, &value);
+
+        self.impl_trait(
+            trait_.clone(),
+            trait_,
+            quote::quote! {
+                #[inline]
+                fn #method<__I: ::core::iter::Iterator<Item = Self>>(iter: __I) -> Self {
+                    #construct
+                }
+            },
+        )
+    }
+}
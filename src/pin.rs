@@ -0,0 +1,125 @@
+//! Expansion of `#[deref(pin)]` and `#[deref(future)]`.
+
+use crate::{DerefTarget, Errors};
+
+/// Expands `project` with `#[deref(pin)]`, and `Future` with `#[deref(future)]`.
+///
+/// Projecting is only sound if the target field is pinned structurally, the
+/// generated code enforces it:
+/// - the type is `Unpin` only if the target field is, a conflicting impl fails,
+/// - the type can't implement `Drop`, which could move the field out of the pin,
+/// - the type can't be `#[repr(packed)]`, which moves fields to access them.
+///
+/// `DerefMut` requires the target field to be `Unpin`, see [`DerefTarget::unpin_bound`].
+pub fn expand(
+    input: &syn::DeriveInput,
+    target: &DerefTarget,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let options = &target.options;
+    let mut errors = Errors::default();
+
+    let pin = match (&options.pin, &options.future) {
+        (Some(pin), _) => pin,
+        (None, Some(future)) => {
+            return Err(syn::Error::new(future.span(), "`future` requires `pin`"));
+        }
+        (None, None) => return Ok(proc_macro2::TokenStream::new()),
+    };
+
+    if target.is_nested() {
+        errors.push(syn::Error::new(
+            pin.span(),
+            "`pin` can't be combined with a nested `path`",
+        ));
+    }
+
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            let packed = list.nested.iter().any(|meta| match meta {
+                syn::NestedMeta::Meta(meta) => meta.path().is_ident("packed"),
+                syn::NestedMeta::Lit(_) => false,
+            });
+
+            if packed {
+                errors.push(syn::Error::new_spanned(
+                    attr,
+                    "`pin` can't be used on #[repr(packed)] types",
+                ));
+            }
+        }
+    }
+
+    errors.finish()?;
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let field_ty = &target.field_ty;
+    let field = target.field_ref_of(&quote::quote!(this), true);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // the lifetime keeps the bound from being checked eagerly, which would fail
+    // for a concrete `!Unpin` field
+    let mut unpin_generics = input.generics.clone();
+    unpin_generics.params.insert(0, syn::parse_quote!('__pin));
+    unpin_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(
+            ::core::marker::PhantomData<(&'__pin (), #field_ty)>: ::core::marker::Unpin
+        ));
+    let (unpin_impl_generics, _, unpin_where_clause) = unpin_generics.split_for_impl();
+
+    let mut future = None;
+
+    if options.future.is_some() {
+        let mut generics = input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#field_ty: ::core::future::Future));
+        let (_, _, where_clause) = generics.split_for_impl();
+
+        future = Some(quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::future::Future for #ident #ty_generics #where_clause {
+                type Output = <#field_ty as ::core::future::Future>::Output;
+
+                #[inline]
+                fn poll(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> ::core::task::Poll<Self::Output> {
+                    <#field_ty as ::core::future::Future>::poll(Self::project(self), cx)
+                }
+            }
+        });
+    }
+
+    Ok(quote::quote! {
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Returns the pinned target field.
+            #[inline]
+            #vis fn project(self: ::core::pin::Pin<&mut Self>) -> ::core::pin::Pin<&mut #field_ty> {
+                // SAFETY: the target field is pinned structurally, the type is only
+                // `Unpin` if the field is, doesn't implement `Drop` and isn't packed
+                unsafe { self.map_unchecked_mut(|this| #field) }
+            }
+        }
+
+        #[automatically_derived]
+        impl #unpin_impl_generics ::core::marker::Unpin for #ident #ty_generics #unpin_where_clause {}
+
+        const _: () = {
+            trait MustNotImplDrop {}
+
+            #[allow(drop_bounds)]
+            impl<T: ::core::ops::Drop> MustNotImplDrop for T {}
+
+            impl #impl_generics MustNotImplDrop for #ident #ty_generics #where_clause {}
+        };
+
+        #future
+    })
+}
@@ -0,0 +1,630 @@
+//! Selection of the field to deref to.
+
+use crate::{attr, Errors};
+
+/// A field of a struct or enum variant.
+struct Field {
+    member: syn::Member,
+    ty: syn::Type,
+    options: attr::FieldOptions,
+}
+
+impl Field {
+    fn parse(fields: &syn::Fields, errors: &mut Errors) -> Vec<Self> {
+        let fields = fields.iter().enumerate().map(|(i, f)| {
+            let member = member(i, f);
+            let options = attr::FieldOptions::parse(&f.attrs, errors);
+
+            Self {
+                member,
+                ty: f.ty.clone(),
+                options,
+            }
+        });
+
+        fields.collect()
+    }
+
+    /// Selects the field to deref to, `ident` is the struct or variant owning the fields.
+    ///
+    /// `chosen` is the field named by `#[deref(field = ...)]` on the type.
+    fn select(
+        ident: &syn::Ident,
+        fields: Vec<Self>,
+        chosen: Option<&syn::Member>,
+    ) -> syn::Result<Self> {
+        if let Some(chosen) = chosen {
+            return Self::select_chosen(ident, fields, chosen);
+        }
+
+        let (mut marked, unmarked): (Vec<_>, Vec<_>) = fields
+            .into_iter()
+            .partition(|field| field.options.is_marked());
+
+        match marked.len() {
+            0 => {}
+            1 => return Ok(marked.pop().unwrap()),
+            _ => {
+                let mut errors = Errors::default();
+
+                for field in marked {
+                    errors.push(syn::Error::new_spanned(
+                        &field.options.attr,
+                        "expected exactly one field with #[deref] attribute, found multiple",
+                    ));
+                }
+
+                return Err(errors.take());
+            }
+        }
+
+        // without a marked field, select the only field that isn't ignored,
+        // marker fields are only selected when they're the only option
+        let mut candidates = unmarked
+            .into_iter()
+            .filter(|field| field.options.ignored().is_none())
+            .collect::<Vec<_>>();
+
+        let names = candidates
+            .iter()
+            .map(|field| format!("`{}`", member_name(&field.member)))
+            .collect::<Vec<_>>();
+
+        if candidates.len() > 1 {
+            candidates.retain(|field| !is_marker(&field.ty));
+        }
+
+        if candidates.len() == 1 {
+            return Ok(candidates.pop().unwrap());
+        }
+
+        if names.is_empty() {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("every field of `{}` is ignored", ident),
+            ));
+        }
+
+        Err(syn::Error::new(
+            ident.span(),
+            format!(
+                "expected exactly one field with #[deref] attribute in `{}`\n\n\
+                 help: add #[deref] to one of the fields: {}",
+                ident,
+                names.join(", "),
+            ),
+        ))
+    }
+
+    fn select_chosen(
+        ident: &syn::Ident,
+        fields: Vec<Self>,
+        chosen: &syn::Member,
+    ) -> syn::Result<Self> {
+        let candidates = fields
+            .iter()
+            .map(|field| format!("`{}`", member_name(&field.member)))
+            .collect::<Vec<_>>();
+
+        let mut errors = Errors::default();
+        let mut selected = None;
+
+        for field in fields {
+            if field.member == *chosen {
+                if let Some((key, span)) = field.options.ignored() {
+                    errors.push(syn::Error::new(
+                        span,
+                        format!(
+                            "field `{}` is excluded by `{}`, but selected with #[deref(field = {0})]",
+                            member_name(chosen),
+                            key,
+                        ),
+                    ));
+                }
+
+                selected = Some(field);
+            } else if field.options.is_marked() {
+                errors.push(syn::Error::new_spanned(
+                    &field.options.attr,
+                    format!(
+                        "#[deref] conflicts with #[deref(field = {})] on `{}`",
+                        member_name(chosen),
+                        ident,
+                    ),
+                ));
+            }
+        }
+
+        if selected.is_none() {
+            errors.push(syn::Error::new_spanned(
+                chosen,
+                format!(
+                    "no field `{}` in `{}`\n\n\
+                     help: the fields are: {}",
+                    member_name(chosen),
+                    ident,
+                    candidates.join(", "),
+                ),
+            ));
+        }
+
+        errors.finish()?;
+        Ok(selected.unwrap())
+    }
+}
+
+/// Returns whether `ty` is a marker type, `PhantomData<T>` or `PhantomPinned`.
+fn is_marker(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(ty) => match ty.path.segments.last() {
+            Some(segment) => segment.ident == "PhantomData" || segment.ident == "PhantomPinned",
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns the member of the `i`th field `field`.
+pub fn member(i: usize, field: &syn::Field) -> syn::Member {
+    match field.ident {
+        Some(ref ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(syn::Index::from(i)),
+    }
+}
+
+fn member_name(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) => ident.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// How the target field is reached from `self`.
+enum Access {
+    /// `self.member`.
+    Struct(syn::Member),
+    /// `match self { Enum::Variant { member: field, .. } => field }`, by the name of
+    /// the enum as `Self` may be a reference to it.
+    Enum(syn::Ident, Vec<(syn::Ident, syn::Member)>),
+}
+
+/// How the `Target` is reached from the target field.
+struct Mode {
+    /// `forward`, deref through the field's own `Deref`.
+    forward: bool,
+    /// `target = Type`, coerce to `Type`.
+    target: Option<syn::Type>,
+}
+
+impl Mode {
+    fn new(options: &attr::TypeOptions, field: &attr::FieldOptions) -> Self {
+        let forward = field.forward.is_some() || options.forward.is_some();
+        let target = field.target.as_ref().or(options.target.as_ref());
+
+        Self {
+            forward,
+            target: target.cloned(),
+        }
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        let same_target = match (&self.target, &other.target) {
+            (Some(a), Some(b)) => same_type(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        self.forward == other.forward && same_target
+    }
+}
+
+/// The field a type derefs to.
+pub struct DerefTarget {
+    /// The type of the target field.
+    pub field_ty: syn::Type,
+    /// Fields of the target field leading to the target, from `path = a.b.c`.
+    nested: Vec<syn::Member>,
+    mode: Mode,
+    access: Access,
+    /// Options on the type.
+    pub options: attr::TypeOptions,
+}
+
+impl DerefTarget {
+    pub fn get(input: &syn::DeriveInput) -> syn::Result<Self> {
+        let mut errors = Errors::default();
+
+        let options = attr::TypeOptions::parse(&input.attrs, &mut errors);
+
+        if let (Some(field), Some(_)) = (&options.field, &options.path) {
+            errors.push(syn::Error::new_spanned(
+                field,
+                "`field` can't be combined with `path`",
+            ));
+        }
+
+        let target = match input.data {
+            syn::Data::Struct(ref data) => Self::get_struct(input, options, data, &mut errors),
+            syn::Data::Enum(ref data) => Self::get_enum(input, options, data, &mut errors),
+            syn::Data::Union(ref data) => Err(syn::Error::new(
+                data.union_token.span,
+                "can only be derived for structs and enums",
+            )),
+        };
+
+        let target = target.and_then(|mut target| {
+            if let Some(ref path) = target.options.path {
+                if !path.nested.is_empty() && target.mode.target.is_none() {
+                    return Err(syn::Error::new_spanned(
+                        &path.field,
+                        "`path` to a nested field requires `target = Type`",
+                    ));
+                }
+
+                target.nested = path.nested.clone();
+            }
+
+            Ok(target)
+        });
+
+        match target {
+            Ok(target) => errors.finish().map(|_| target),
+            Err(err) => {
+                errors.push(err);
+                Err(errors.take())
+            }
+        }
+    }
+
+    /// The field chosen on the type, with `field = ...` or `path = ...`.
+    fn chosen(options: &attr::TypeOptions) -> Option<&syn::Member> {
+        let path = options.path.as_ref().map(|path| &path.field);
+        path.or(options.field.as_ref())
+    }
+
+    fn get_struct(
+        input: &syn::DeriveInput,
+        options: attr::TypeOptions,
+        data: &syn::DataStruct,
+        errors: &mut Errors,
+    ) -> syn::Result<Self> {
+        match data.fields {
+            syn::Fields::Named(ref fields) if fields.named.is_empty() => Err(syn::Error::new(
+                fields.brace_token.span,
+                "cannot be derived for structs without fields",
+            )),
+            syn::Fields::Unnamed(ref fields) if fields.unnamed.is_empty() => Err(syn::Error::new(
+                fields.paren_token.span,
+                "cannot be derived for structs without fields",
+            )),
+            syn::Fields::Unit => Err(syn::Error::new(
+                input.ident.span(),
+                "cannot be derived for unit structs",
+            )),
+            _ => {
+                let fields = Field::parse(&data.fields, errors);
+                let field = Field::select(&input.ident, fields, Self::chosen(&options))?;
+
+                Ok(Self {
+                    mode: Mode::new(&options, &field.options),
+                    nested: Vec::new(),
+                    field_ty: field.ty,
+                    access: Access::Struct(field.member),
+                    options,
+                })
+            }
+        }
+    }
+
+    fn get_enum(
+        input: &syn::DeriveInput,
+        options: attr::TypeOptions,
+        data: &syn::DataEnum,
+        errors: &mut Errors,
+    ) -> syn::Result<Self> {
+        if data.variants.is_empty() {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "cannot be derived for enums without variants",
+            ));
+        }
+
+        let mut target: Option<(syn::Ident, syn::Type, Mode)> = None;
+        let mut arms = Vec::new();
+
+        for variant in data.variants.iter() {
+            for attr in variant.attrs.iter() {
+                if attr.path.is_ident(attr::ATTR_NAME) {
+                    errors.push(syn::Error::new_spanned(
+                        attr,
+                        "#[deref] is not allowed on variants, mark a field instead",
+                    ));
+                }
+            }
+
+            if variant.fields.is_empty() {
+                errors.push(syn::Error::new(
+                    variant.ident.span(),
+                    format!("variant `{}` has no field to deref to", variant.ident),
+                ));
+
+                continue;
+            }
+
+            let fields = Field::parse(&variant.fields, errors);
+            let field = match Field::select(&variant.ident, fields, Self::chosen(&options)) {
+                Ok(field) => field,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+
+            let mode = Mode::new(&options, &field.options);
+
+            match target {
+                Some((ref first, ref ty, _)) if !same_type(ty, &field.ty) => {
+                    errors.push(syn::Error::new_spanned(
+                        &field.ty,
+                        format!(
+                            "mismatched deref target in variant `{}`, expected `{}` like variant `{}`",
+                            variant.ident,
+                            quote::quote!(#ty),
+                            first,
+                        ),
+                    ));
+                }
+                Some((ref first, _, ref first_mode)) if !first_mode.same(&mode) => {
+                    errors.push(syn::Error::new(
+                        variant.ident.span(),
+                        format!(
+                            "mismatched deref options in variant `{}`, \
+                             they must match variant `{}`\n\n\
+                             help: set the options on the enum instead, e.g. #[deref(forward)]",
+                            variant.ident, first,
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => target = Some((variant.ident.clone(), field.ty, mode)),
+            }
+
+            arms.push((variant.ident.clone(), field.member));
+        }
+
+        // if no variant has a target, every variant has pushed an error
+        let (_, field_ty, mode) = target.ok_or_else(|| errors.take())?;
+
+        Ok(Self {
+            field_ty,
+            nested: Vec::new(),
+            mode,
+            access: Access::Enum(input.ident.clone(), arms),
+            options,
+        })
+    }
+
+    /// Returns the member of the target field of a struct, if the target is the
+    /// field itself and not a nested field in it.
+    pub fn struct_member(&self) -> Option<&syn::Member> {
+        match self.access {
+            Access::Struct(ref member) if self.nested.is_empty() => Some(member),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the target is the target field itself, without `forward`,
+    /// `target = Type` or a nested `path`.
+    pub fn is_field(&self) -> bool {
+        self.nested.is_empty() && !self.mode.forward && self.mode.target.is_none()
+    }
+
+    /// Returns whether the target is a nested field in the target field.
+    pub fn is_nested(&self) -> bool {
+        !self.nested.is_empty()
+    }
+
+    /// Returns assertions that the type implements the traits of the derives
+    /// generating the items of its options, `derive` being the one expanded now.
+    ///
+    /// Every derive reads the options on the type, but only one of them generates
+    /// the items of each option, see [`attr::OPTION_DERIVES`]. Without it the
+    /// option would be ignored silently.
+    pub fn require_derives(
+        &self,
+        input: &syn::DeriveInput,
+        derive: Option<&str>,
+    ) -> proc_macro2::TokenStream {
+        let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+        let mut required = Vec::new();
+        let mut checks = Vec::new();
+
+        for key in self.options.keys.iter() {
+            let owner = attr::OPTION_DERIVES
+                .iter()
+                .find(|(option, _)| key == option)
+                .map(|(_, owner)| *owner);
+
+            let owner = match owner {
+                Some(owner) if Some(owner) != derive && !required.contains(&owner) => owner,
+                _ => continue,
+            };
+            required.push(owner);
+
+            // the error points at the option
+            let span = key.span();
+            let mut ident = input.ident.clone();
+            ident.set_span(span);
+            let this = quote::quote!(#ident #ty_generics);
+
+            let method = match owner {
+                "Deref" => quote::quote_spanned!(span=> <#this as ::core::ops::Deref>::deref),
+                "Borrow" => {
+                    let target_ty = self.target_ty();
+                    quote::quote_spanned!(span=> <#this as ::core::borrow::Borrow<#target_ty>>::borrow)
+                }
+                "Index" => {
+                    let index = &self.options.index;
+                    quote::quote_spanned!(span=> <#this as ::core::ops::Index<#index>>::index)
+                }
+                _ => unreachable!("unknown derive `{}`", owner),
+            };
+
+            checks.push(quote::quote_spanned!(span=> let _ = #method;));
+        }
+
+        if checks.is_empty() {
+            return proc_macro2::TokenStream::new();
+        }
+
+        // taking the type implies the bounds of its lifetimes
+        let ident = &input.ident;
+
+        quote::quote! {
+            const _: () = {
+                #[allow(dead_code)]
+                fn require_derives #impl_generics (_: &#ident #ty_generics) #where_clause {
+                    #(#checks)*
+                }
+            };
+        }
+    }
+
+    /// Returns the bound `DerefMut` needs with `#[deref(pin)]`, so a `!Unpin`
+    /// target field can't be reached mutably outside of `project`.
+    pub fn unpin_bound(&self) -> Option<syn::WherePredicate> {
+        let field_ty = &self.field_ty;
+
+        self.options
+            .pin
+            .as_ref()
+            .map(|_| syn::parse_quote!(#field_ty: ::core::marker::Unpin))
+    }
+
+    /// Returns the `Target` type.
+    pub fn target_ty(&self) -> syn::Type {
+        let field_ty = &self.field_ty;
+
+        match (&self.mode.target, self.mode.forward) {
+            // `&dyn A + B` is ambiguous, the type may end up behind a reference
+            (Some(syn::Type::TraitObject(ty)), _) if ty.bounds.len() > 1 => {
+                syn::parse_quote!((#ty))
+            }
+            (Some(ty), _) => ty.clone(),
+            (None, true) => syn::parse_quote!(<#field_ty as ::core::ops::Deref>::Target),
+            (None, false) => field_ty.clone(),
+        }
+    }
+
+    /// Returns an expression borrowing the target of `self`, mutably if `mutable`.
+    ///
+    /// With `target = Type` the expression relies on being coerced to `&Type`.
+    pub fn target_ref(&self, mutable: bool) -> proc_macro2::TokenStream {
+        self.target_ref_of(&quote::quote!(self), mutable)
+    }
+
+    /// Returns an expression borrowing the target of `receiver`, a reference to
+    /// `Self`, mutably if `mutable`.
+    pub fn target_ref_of(
+        &self,
+        receiver: &proc_macro2::TokenStream,
+        mutable: bool,
+    ) -> proc_macro2::TokenStream {
+        let field_ref = self.field_ref_of(receiver, mutable);
+
+        match (self.mode.forward, mutable) {
+            (false, _) => field_ref,
+            (true, false) => quote::quote!(::core::ops::Deref::deref(#field_ref)),
+            (true, true) => quote::quote!(::core::ops::DerefMut::deref_mut(#field_ref)),
+        }
+    }
+
+    /// Returns an expression borrowing the target field of `receiver`, a reference
+    /// to `Self`, mutably if `mutable`.
+    pub fn field_ref_of(
+        &self,
+        receiver: &proc_macro2::TokenStream,
+        mutable: bool,
+    ) -> proc_macro2::TokenStream {
+        let mutability = mutable.then(<syn::Token![mut]>::default);
+        self.access_of(receiver, &quote::quote!(&#mutability))
+    }
+
+    /// Returns an expression moving the target field out of `receiver`, a `Self`.
+    pub fn field_of(&self, receiver: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        self.access_of(receiver, &proc_macro2::TokenStream::new())
+    }
+
+    /// Returns an expression accessing the target field of `receiver` through `prefix`,
+    /// which is `&`, `&mut` or nothing.
+    fn access_of(
+        &self,
+        receiver: &proc_macro2::TokenStream,
+        prefix: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let nested = &self.nested;
+
+        match self.access {
+            Access::Struct(ref member) => quote::quote!(#prefix #receiver.#member #(.#nested)*),
+            Access::Enum(ref ident, ref arms) => {
+                // the binding already has the mode of `receiver`
+                let field = if nested.is_empty() {
+                    quote::quote!(field)
+                } else {
+                    quote::quote!(#prefix field #(.#nested)*)
+                };
+
+                let arms = arms.iter().map(|(variant, member)| {
+                    quote::quote!(#ident::#variant { #member: field, .. } => #field,)
+                });
+
+                quote::quote! {
+                    match #receiver {
+                        #(#arms)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn same_type(a: &syn::Type, b: &syn::Type) -> bool {
+    quote::quote!(#a).to_string() == quote::quote!(#b).to_string()
+}
+
+/// Returns whether `ty` leaves one of the type parameters of `generics` uncovered,
+/// being the parameter itself or reaching it only through the fundamental types
+/// `&`, `&mut`, `Box` and `Pin`.
+///
+/// The orphan rules reject implementing a foreign trait for such a type.
+pub fn is_uncovered(ty: &syn::Type, generics: &syn::Generics) -> bool {
+    match ty {
+        syn::Type::Reference(ty) => is_uncovered(&ty.elem, generics),
+        syn::Type::Paren(ty) => is_uncovered(&ty.elem, generics),
+        syn::Type::Group(ty) => is_uncovered(&ty.elem, generics),
+        syn::Type::Path(ty) if ty.qself.is_none() => {
+            if generics
+                .type_params()
+                .any(|param| ty.path.is_ident(&param.ident))
+            {
+                return true;
+            }
+
+            let segment = match ty.path.segments.last() {
+                Some(segment) if segment.ident == "Box" || segment.ident == "Pin" => segment,
+                _ => return false,
+            };
+
+            match segment.arguments {
+                syn::PathArguments::AngleBracketed(ref args) => {
+                    args.args.iter().any(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => is_uncovered(ty, generics),
+                        _ => false,
+                    })
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,43 @@
+//! The generated code must not clash with the names used by the type.
+
+use std::{collections::HashSet, marker::PhantomData};
+
+use deref_derive::{AsRef, Borrow, Deref};
+
+#[derive(Deref, Borrow)]
+#[deref(borrow(Hash, PartialEq, Eq))]
+struct Key<H> {
+    #[deref]
+    id: u32,
+    hasher: H,
+}
+
+#[derive(Clone, Copy, Deref)]
+#[deref(ops(Add, Sum))]
+struct Total<I>(#[deref] u32, PhantomData<I>);
+
+#[allow(non_camel_case_types)]
+#[derive(Deref, AsRef)]
+#[deref(fmt(Display))]
+struct r#loop(u32);
+
+#[test]
+fn type_params() {
+    let mut keys = HashSet::new();
+    keys.insert(Key { id: 1, hasher: () });
+
+    assert!(keys.contains(&1));
+    assert_eq!(keys.iter().next().map(|key| key.hasher), Some(()));
+
+    let totals = [Total::<()>(1, PhantomData), Total(2, PhantomData)];
+
+    assert_eq!(*totals.into_iter().sum::<Total<()>>(), 3);
+}
+
+#[test]
+fn raw_idents() {
+    let value = r#loop(1);
+
+    assert_eq!(value.to_string(), "1");
+    assert_eq!(value.as_ref(), &1);
+}
@@ -0,0 +1,156 @@
+//! The derives must only refer to `::core`, so they work in `#![no_std]` crates.
+
+#![no_std]
+
+use core::borrow::Borrow;
+
+use deref_derive::{
+    AsMut, AsRef, Borrow, BorrowMut, Deref, DerefMut, DoubleEndedIterator, ExactSizeIterator,
+    FusedIterator, Index, IndexMut, IntoIterator, Iterator,
+};
+
+#[derive(Deref, DerefMut, AsRef, AsMut)]
+struct Counter(u32);
+
+#[derive(Deref, DerefMut)]
+struct Buf {
+    #[deref(target = [u8])]
+    data: [u8; 4],
+    len: usize,
+}
+
+#[derive(Borrow, BorrowMut)]
+#[deref(borrow(Hash, PartialEq, Eq, PartialOrd, Ord))]
+struct Key {
+    #[deref]
+    id: u32,
+    hits: u32,
+}
+
+#[derive(Deref)]
+#[deref(forward)]
+struct Bytes(&'static [u8]);
+
+#[derive(Deref, DerefMut)]
+enum Value {
+    Small(u32),
+    Large(u64, #[deref] u32),
+}
+
+#[test]
+fn structs() {
+    let mut counter = Counter(1);
+    *counter += 1;
+
+    assert_eq!(*counter, 2);
+    assert_eq!(Bytes(b"bytes").len(), 5);
+
+    *counter.as_mut() += 1;
+    assert_eq!(counter.as_ref(), &3);
+}
+
+#[test]
+fn target() {
+    let mut buf = Buf {
+        data: [0; 4],
+        len: 1,
+    };
+    buf[0] = 1;
+
+    assert_eq!(&buf[..buf.len], [1]);
+}
+
+#[test]
+fn enums() {
+    let mut value = Value::Large(0, 1);
+    *value += 1;
+
+    assert_eq!(*value, 2);
+    assert_eq!(*Value::Small(3), 3);
+
+    if let Value::Large(large, _) = value {
+        assert_eq!(large, 0);
+    }
+}
+
+#[test]
+fn borrow() {
+    let a = Key { id: 1, hits: 0 };
+    let b = Key { id: 1, hits: 1 };
+
+    assert!(a == b);
+    assert!(a < Key { id: 2, hits: 0 });
+    assert_eq!(Borrow::<u32>::borrow(&a), &b.id);
+    assert_eq!(a.hits, 0);
+}
+
+#[derive(Index, IndexMut)]
+struct Row([u32; 4]);
+
+#[derive(Deref, IntoIterator)]
+struct Bits(&'static [bool]);
+
+#[derive(Iterator, DoubleEndedIterator, ExactSizeIterator, FusedIterator)]
+struct Countdown(core::ops::Range<u32>);
+
+#[derive(Clone, Copy, Deref)]
+#[deref(
+    fmt(Display, Debug, LowerHex),
+    ops(Add, Neg, AddAssign, Sum),
+    cmp(PartialEq, PartialOrd)
+)]
+struct Meters(i32);
+
+#[derive(Deref)]
+#[deref(pin, future)]
+struct Named<F> {
+    #[deref]
+    future: F,
+    name: &'static str,
+}
+
+#[test]
+fn index() {
+    let mut row = Row([1, 2, 3, 4]);
+    row[0] = 5;
+
+    assert_eq!(row[0], 5);
+    assert_eq!(row[1..3], [2, 3]);
+}
+
+#[test]
+fn iterators() {
+    assert_eq!(
+        Bits(&[true, false]).into_iter().filter(|bit| **bit).count(),
+        1
+    );
+
+    let mut countdown = Countdown(0..3);
+
+    assert_eq!(countdown.len(), 3);
+    assert_eq!(countdown.next_back(), Some(2));
+    assert_eq!(countdown.next(), Some(0));
+}
+
+#[test]
+fn options() {
+    fn assert_fmt<T: core::fmt::Display + core::fmt::Debug + core::fmt::LowerHex>() {}
+    fn assert_future<F: core::future::Future<Output = u32>>() {}
+
+    assert_fmt::<Meters>();
+    assert_future::<Named<core::future::Ready<u32>>>();
+
+    let mut length = Meters(2) + 1;
+    length += Meters(1);
+
+    assert!(-length == -4);
+    assert!(length > 3);
+    assert!([length, length].into_iter().sum::<Meters>() == 8);
+
+    let named = Named {
+        future: core::future::ready(1),
+        name: "ready",
+    };
+
+    assert_eq!(named.name, "ready");
+}
@@ -0,0 +1,7 @@
+//! The errors of the derives, checked against the snapshots in `tests/ui`.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+use deref_derive::AsRef;
+
+#[derive(AsRef)]
+struct Pair {
+    #[as_ref]
+    a: u32,
+    #[as_ref]
+    b: u32,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use deref_derive::AsRef;
+
+#[derive(AsRef)]
+#[as_ref(forward)]
+struct Name(String);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use deref_derive::AsRef;
+
+#[derive(AsRef)]
+enum Name {
+    #[as_ref]
+    Short(String),
+    Long(String),
+}
+
+fn main() {}
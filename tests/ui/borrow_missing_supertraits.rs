@@ -0,0 +1,11 @@
+use deref_derive::Borrow;
+
+#[derive(Borrow)]
+#[deref(borrow(Hash, Ord))]
+struct Key {
+    #[deref]
+    id: u32,
+    hits: u32,
+}
+
+fn main() {}
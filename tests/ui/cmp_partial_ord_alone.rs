@@ -0,0 +1,7 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(cmp(PartialOrd))]
+struct UserId(String);
+
+fn main() {}
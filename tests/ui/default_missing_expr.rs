@@ -0,0 +1,11 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(from)]
+struct Cached {
+    value: String,
+    #[deref(default =, ignore)]
+    hits: u32,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use deref_derive::{Deref, DerefMut};
+use std::marker::PhantomPinned;
+
+#[derive(Deref, DerefMut)]
+#[deref(pin)]
+struct Pinned(PhantomPinned);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+enum Foo {
+    Number(u32),
+    Text(String),
+}
+
+fn main() {}
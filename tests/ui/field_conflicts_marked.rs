@@ -0,0 +1,11 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(field = other_field)]
+struct Foo {
+    #[deref]
+    field: u32,
+    other_field: String,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(field = 2)]
+struct Foo(u32, String);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(field = missing)]
+struct Foo {
+    field: u32,
+    other_field: String,
+}
+
+fn main() {}
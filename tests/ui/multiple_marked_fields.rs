@@ -0,0 +1,11 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+struct Foo {
+    #[deref]
+    field: u32,
+    #[deref]
+    other_field: String,
+}
+
+fn main() {}
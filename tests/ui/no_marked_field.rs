@@ -0,0 +1,9 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+struct Foo {
+    field: u32,
+    other_field: String,
+}
+
+fn main() {}
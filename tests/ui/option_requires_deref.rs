@@ -0,0 +1,7 @@
+use deref_derive::Borrow;
+
+#[derive(Borrow)]
+#[deref(fmt(Display), from)]
+struct Meters(u32);
+
+fn main() {}
@@ -0,0 +1,7 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(index = usize, borrow(Hash))]
+struct Items(Vec<u32>);
+
+fn main() {}
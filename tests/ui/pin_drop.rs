@@ -0,0 +1,11 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(pin)]
+struct Guarded<F>(F);
+
+impl<F> Drop for Guarded<F> {
+    fn drop(&mut self) {}
+}
+
+fn main() {}
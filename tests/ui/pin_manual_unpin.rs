@@ -0,0 +1,10 @@
+use deref_derive::Deref;
+use std::marker::PhantomPinned;
+
+#[derive(Deref)]
+#[deref(pin)]
+struct Pinned(PhantomPinned);
+
+impl Unpin for Pinned {}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+#[deref(pin)]
+#[repr(packed)]
+struct Packed(u32);
+
+fn main() {}
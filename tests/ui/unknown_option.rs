@@ -0,0 +1,9 @@
+use deref_derive::Deref;
+
+#[derive(Deref)]
+struct Foo {
+    #[deref(typo)]
+    field: u32,
+}
+
+fn main() {}